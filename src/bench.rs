@@ -0,0 +1,116 @@
+use std::{
+    fs,
+    time::{Duration, Instant},
+};
+
+use crate::BoxedError;
+use aoc_framework::{AocSolution, AocStringIter, AocTask, BoxedAocTask};
+
+#[allow(dead_code)]
+pub struct TimedSolution {
+    pub answer: AocSolution,
+    pub elapsed: Duration,
+}
+
+// Only the solution call is measured, reading the input is left to the caller
+#[allow(dead_code)]
+pub fn run_timed(
+    task: &dyn AocTask,
+    input: AocStringIter,
+    phase: usize,
+) -> Result<TimedSolution, BoxedError> {
+    let start = Instant::now();
+    let answer = task.solution(input, phase)?;
+    Ok(TimedSolution {
+        answer,
+        elapsed: start.elapsed(),
+    })
+}
+
+// Median wall-clock time of the given phase over the given number of runs
+#[allow(dead_code)]
+pub fn bench_day(task: &dyn AocTask, input: &str, phase: usize, runs: usize) -> Duration {
+    median_run(task, input, phase, runs).map_or(Duration::ZERO, |run| run.elapsed)
+}
+
+// The run with the median time, None if there were no runs
+fn median_run(task: &dyn AocTask, input: &str, phase: usize, runs: usize) -> Option<TimedSolution> {
+    let lines: Vec<String> = input.lines().map(String::from).collect();
+    let mut timed: Vec<TimedSolution> = (0..runs)
+        .map(|_| {
+            run_timed(task, Box::new(lines.clone().into_iter()), phase)
+                .expect("Benchmarked solution should succeed")
+        })
+        .collect();
+    timed.sort_by_key(|run| run.elapsed);
+    (runs > 0).then(|| timed.swap_remove(runs / 2))
+}
+
+// Prints the median times of both phases over the real inputs, tasks without an input are
+// skipped
+#[allow(dead_code)]
+pub fn bench_all(tasks: &[BoxedAocTask], runs: usize) {
+    println!("{:<12} {:>14} {:>14}", "task", "phase 1", "phase 2");
+    for task in tasks {
+        let directory = task.directory();
+        let Ok(input) = fs::read_to_string(directory.join("in")) else {
+            continue;
+        };
+        let [first, second] = [1, 2].map(|phase| bench_day(task.as_ref(), &input, phase, runs));
+        println!(
+            "{:<12} {:>14?} {:>14?}",
+            directory
+                .file_name()
+                .map_or_else(String::new, |name| name.to_string_lossy().into_owned()),
+            first,
+            second
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        fs,
+        time::{Duration, Instant},
+    };
+
+    use aoc_framework::AocTask;
+
+    use super::{bench_day, median_run, run_timed};
+    use crate::{day_01::Day01, day_05::Day05};
+
+    #[test]
+    fn bench_single_run() {
+        let input = "two1nine\n4nineeightseven2\nzoneight234";
+        let run = median_run(&Day01, input, 2, 1).unwrap();
+        // 29 + 42 + 14
+        assert_eq!(run.answer.to_string(), "85");
+        assert!(median_run(&Day01, input, 2, 0).is_none());
+        assert_eq!(bench_day(&Day01, input, 2, 0), Duration::ZERO);
+    }
+
+    #[test]
+    fn timed_matches_untimed() {
+        let input = fs::read_to_string("tasks/day_05/example_in").unwrap();
+        let lines = move || {
+            Box::new(
+                input
+                    .lines()
+                    .map(String::from)
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+            )
+        };
+        for phase in [1, 2] {
+            let start = Instant::now();
+            let timed = run_timed(&Day05, lines(), phase).unwrap();
+            assert!(timed.elapsed <= start.elapsed());
+            let untimed = Day05.solution(lines(), phase).unwrap();
+            assert_eq!(timed.answer.to_string(), untimed.to_string());
+        }
+
+        // A single run could be shorter than the clock's resolution, so it gets a few tries
+        assert!((0..100).any(|_| run_timed(&Day05, lines(), 2).unwrap().elapsed > Duration::ZERO));
+    }
+}
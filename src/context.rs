@@ -0,0 +1,45 @@
+use std::fmt::Display;
+
+use crate::BoxedError;
+
+pub trait Context<T> {
+    // Turns the error into a BoxedError, prefixing its message with msg
+    fn context(self, msg: &str) -> Result<T, BoxedError>;
+}
+
+impl<T, E: Display> Context<T> for Result<T, E> {
+    fn context(self, msg: &str) -> Result<T, BoxedError> {
+        self.map_err(|err| format!("{msg}: {err}").into())
+    }
+}
+
+// A missing value has no error of its own, so msg is the whole message
+impl<T> Context<T> for Option<T> {
+    fn context(self, msg: &str) -> Result<T, BoxedError> {
+        self.ok_or_else(|| msg.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Context;
+
+    #[test]
+    fn prefixed_error() {
+        let result: Result<u8, _> = "256".parse::<u8>().context("parsing a byte");
+        assert_eq!(
+            result.map_err(|err| err.to_string()),
+            Err("parsing a byte: number too large to fit in target type".to_string())
+        );
+        assert_eq!(
+            "255".parse::<u8>().context("parsing a byte").ok(),
+            Some(255)
+        );
+        assert_eq!(
+            None::<u8>
+                .context("missing byte")
+                .map_err(|err| err.to_string()),
+            Err("missing byte".to_string())
+        );
+    }
+}
@@ -3,15 +3,16 @@
 
 use std::{
     cmp::Ordering::{Equal, Greater, Less},
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     ops,
     ops::Range,
     path::PathBuf,
 };
 
-use crate::BoxedError;
+use crate::{input::blank_separated_groups, BoxedError};
 use aoc_framework::{traits::*, AocSolution, AocStringIter, AocTask};
 use itertools::Itertools;
+use rayon::prelude::*;
 
 pub struct Day05;
 
@@ -28,6 +29,14 @@ impl MappingRange {
             offset: dest - src,
         }
     }
+
+    // Produces a MappingRange which maps the destination range back onto the source range
+    fn inverted(&self) -> Self {
+        Self {
+            range: (self.range.start + self.offset)..(self.range.end + self.offset),
+            offset: -self.offset,
+        }
+    }
 }
 
 impl ops::Shr<&MappingRange> for i64 {
@@ -141,6 +150,34 @@ impl TryFrom<Vec<String>> for Mapping {
     }
 }
 
+impl Mapping {
+    // Returns the parts of the domain which aren't covered by any MappingRange
+    #[allow(dead_code)]
+    fn coverage_gaps(&self, domain: Range<i64>) -> Vec<Range<i64>> {
+        let mut gaps = vec![];
+        let mut cursor = domain.start;
+        for mapping_range in self
+            .ranges
+            .iter()
+            .map(|mapping_range| &mapping_range.range)
+            .sorted_by_key(|range| range.start)
+        {
+            if cursor >= domain.end {
+                break;
+            }
+            if mapping_range.start > cursor {
+                gaps.push(cursor..mapping_range.start.min(domain.end));
+            }
+            cursor = cursor.max(mapping_range.end);
+        }
+
+        if cursor < domain.end {
+            gaps.push(cursor..domain.end);
+        }
+        gaps
+    }
+}
+
 #[derive(Debug)]
 struct Almanac {
     start_ids: Vec<i64>,
@@ -152,34 +189,20 @@ struct Almanac {
 impl<'src> TryFrom<AocStringIter<'src>> for Almanac {
     type Error = BoxedError;
 
-    fn try_from(mut value: AocStringIter<'src>) -> Result<Self, Self::Error> {
-        let header = value
-            .next()
-            .ok_or("Missing Almanac header")?
-            .replace("seeds: ", "");
+    fn try_from(value: AocStringIter<'src>) -> Result<Self, Self::Error> {
+        let mut groups = blank_separated_groups(value);
+        // Seeds can be spread across multiple lines
+        let header = groups.next().ok_or("Missing Almanac header")?;
 
         let start_ids = header
-            .split_whitespace()
+            .iter()
+            .flat_map(|line| line.trim_start_matches("seeds:").split_whitespace())
             .map(str::parse::<i64>)
             .collect::<Result<Vec<_>, _>>()?;
 
-        // Skip empty row
-        value.next();
-
-        let mut maps = vec![];
-
-        loop {
-            // Mapping ends with newline, take_while will consume it
-            let mapping: Vec<_> = value
-                .by_ref()
-                .take_while(|string| !string.is_empty())
-                .collect();
-            if mapping.is_empty() {
-                break;
-            } else {
-                maps.push(Mapping::try_from(mapping)?);
-            }
-        }
+        let maps = groups
+            .map(Mapping::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
 
         Self::new(start_ids, maps)
     }
@@ -202,40 +225,115 @@ impl Almanac {
             .map(|mapping| (mapping.from.to_string(), mapping))
             .collect();
 
-        Ok(Self {
+        let almanac = Self {
             start_ids,
             maps,
             first_field,
             last_field,
-        })
+        };
+        almanac.validate_chain()?;
+
+        Ok(almanac)
+    }
+
+    // Makes sure the mappings form a single chain leading from the first field to the last one
+    fn validate_chain(&self) -> Result<(), BoxedError> {
+        let mut visited = HashSet::new();
+        let mut field = &self.first_field;
+        while field != &self.last_field {
+            if !visited.insert(field) {
+                return Err(format!("Mapping chain contains a cycle at: {field}").into());
+            }
+
+            field = &self
+                .maps
+                .get(field)
+                .ok_or(format!("Mapping chain is broken, missing mapping: {field}"))?
+                .to;
+        }
+        Ok(())
     }
 
     fn map_ids(&self) -> Result<Vec<i64>, BoxedError> {
-        let mut mapped_ids = vec![];
-        for id in &self.start_ids {
-            let mut mapping_key = &self.first_field;
-            let mut current_id = *id;
-            loop {
-                let mapping = self
-                    .maps
-                    .get(mapping_key)
-                    .ok_or(format!("Couldn't find the required mapping: {mapping_key}"))?;
-
-                // Apply the mapping if a matching ID exists, keep the same ID otherwise
-                let found_id = mapping.ranges.iter().find_map(|range| current_id >> range);
-                if let Some(new_id) = found_id {
-                    current_id = new_id;
-                }
+        self.start_ids.iter().map(|id| self.map_id(*id)).collect()
+    }
 
-                // Move on to the next mapping
-                mapping_key = &mapping.to;
-                if mapping_key == &self.last_field {
-                    mapped_ids.push(current_id);
-                    break;
-                }
+    // Maps the IDs on a thread pool, the sequential map_ids is used by default
+    #[allow(dead_code)]
+    fn map_ids_par(&self) -> Result<Vec<i64>, BoxedError> {
+        self.start_ids
+            .par_iter()
+            .map(|id| self.map_id(*id))
+            .collect()
+    }
+
+    fn map_id(&self, id: i64) -> Result<i64, BoxedError> {
+        let mut mapping_key = &self.first_field;
+        let mut current_id = id;
+        loop {
+            (mapping_key, current_id) = self.map_step(mapping_key, current_id)?;
+            if mapping_key == &self.last_field {
+                return Ok(current_id);
+            }
+        }
+    }
+
+    // Returns the field name and ID at every stage of the mapping, from the first to the last
+    // field. Only meant for diagnostics, map_id skips building the labels
+    #[allow(dead_code)]
+    fn trace_id(&self, id: i64) -> Result<Vec<(String, i64)>, BoxedError> {
+        let mut mapping_key = &self.first_field;
+        let mut current_id = id;
+        let mut trace = vec![(mapping_key.to_string(), current_id)];
+        loop {
+            (mapping_key, current_id) = self.map_step(mapping_key, current_id)?;
+            trace.push((mapping_key.to_string(), current_id));
+            if mapping_key == &self.last_field {
+                return Ok(trace);
+            }
+        }
+    }
+
+    // Applies a single mapping, returning the field it maps to along with the new ID
+    fn map_step(&self, mapping_key: &str, id: i64) -> Result<(&String, i64), BoxedError> {
+        let mapping = self
+            .maps
+            .get(mapping_key)
+            .ok_or(format!("Couldn't find the required mapping: {mapping_key}"))?;
+
+        // Apply the mapping if a matching ID exists, keep the same ID otherwise
+        let new_id = mapping
+            .ranges
+            .iter()
+            .find_map(|range| id >> range)
+            .unwrap_or(id);
+        Ok((&mapping.to, new_id))
+    }
+
+    #[allow(dead_code)]
+    fn unmap_id(&self, location: i64) -> Result<i64, BoxedError> {
+        let mut field = &self.last_field;
+        let mut current_id = location;
+        while field != &self.first_field {
+            let mapping = self
+                .maps
+                .values()
+                .find(|mapping| &mapping.to == field)
+                .ok_or(format!("Couldn't find a mapping leading to: {field}"))?;
+
+            // Apply the inverted mapping if a matching ID exists, keep the same ID otherwise
+            let found_id = mapping
+                .ranges
+                .iter()
+                .find_map(|range| current_id >> &range.inverted());
+            if let Some(new_id) = found_id {
+                current_id = new_id;
             }
+
+            // Move on to the previous mapping
+            field = &mapping.from;
         }
-        Ok(mapped_ids)
+        Ok(current_id)
     }
 
     fn map_ranges(&self) -> Result<Vec<Range<i64>>, BoxedError> {
@@ -255,7 +353,7 @@ impl Almanac {
             let current_range = match ranges_to_map.pop() {
                 Some(range) => range,
                 None => {
-                    // Finished
+                    // Finished, the output of the last mapping is already in mapped_ranges
                     if mapping.to == self.last_field {
                         break;
                     }
@@ -285,10 +383,25 @@ impl Almanac {
                 None => mapped_ranges.push(current_range),
             }
         }
-        Ok(mapped_ranges)
+        Ok(coalesce_ranges(mapped_ranges))
     }
 }
 
+// Sorts the ranges by their start and merges the ones that overlap or touch each other
+fn coalesce_ranges(mut ranges: Vec<Range<i64>>) -> Vec<Range<i64>> {
+    ranges.sort_by_key(|range| range.start);
+    ranges
+        .into_iter()
+        .coalesce(|prev, next| {
+            if next.start <= prev.end {
+                Ok(prev.start..prev.end.max(next.end))
+            } else {
+                Err((prev, next))
+            }
+        })
+        .collect()
+}
+
 impl AocTask for Day05 {
     fn directory(&self) -> PathBuf {
         "tasks/day_05".into()
@@ -309,3 +422,183 @@ impl AocTask for Day05 {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use itertools::Itertools;
+
+    use aoc_framework::AocStringIter;
+
+    use super::{coalesce_ranges, Almanac, Mapping, MappingRange};
+
+    const EXAMPLE_MAPS: &str = "seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+temperature-to-humidity map:
+0 69 1
+1 0 69
+
+humidity-to-location map:
+60 56 37
+56 93 4";
+
+    fn example_maps() -> Vec<Mapping> {
+        EXAMPLE_MAPS
+            .split("\n\n")
+            .map(|block| Mapping::try_from(block.lines().map(String::from).collect_vec()).unwrap())
+            .collect()
+    }
+
+    fn example_almanac() -> Almanac {
+        Almanac::new(vec![79, 14, 55, 13], example_maps()).unwrap()
+    }
+
+    #[test]
+    fn unmap_id_reverses_map_ids() {
+        let almanac = example_almanac();
+        let locations = almanac.map_ids().unwrap();
+        assert_eq!(locations, vec![82, 43, 86, 35]);
+
+        for (location, start_id) in locations.iter().zip(&almanac.start_ids) {
+            assert_eq!(almanac.unmap_id(*location).unwrap(), *start_id);
+        }
+    }
+
+    #[test]
+    fn coalesce_overlapping_ranges() {
+        assert_eq!(
+            coalesce_ranges(vec![0..5, 3..8, 10..12]),
+            vec![0..8, 10..12]
+        );
+    }
+
+    #[test]
+    fn coalesce_touching_ranges() {
+        assert_eq!(coalesce_ranges(vec![0..5, 5..8]), vec![0..8]);
+    }
+
+    #[test]
+    fn coverage_gaps_between_ranges() {
+        let mapping = Mapping {
+            from: "seed".into(),
+            to: "soil".into(),
+            ranges: vec![MappingRange::new(50, 10, 5), MappingRange::new(0, 20, 5)],
+        };
+        assert_eq!(mapping.coverage_gaps(0..30), vec![0..10, 15..20, 25..30]);
+        assert_eq!(mapping.coverage_gaps(10..25), vec![15..20]);
+        assert_eq!(mapping.coverage_gaps(11..14), vec![]);
+    }
+
+    fn empty_mapping(from: &str, to: &str) -> Mapping {
+        Mapping {
+            from: from.into(),
+            to: to.into(),
+            ranges: vec![],
+        }
+    }
+
+    #[test]
+    fn broken_chain() {
+        let maps = vec![
+            empty_mapping("seed", "soil"),
+            empty_mapping("water", "light"),
+        ];
+        assert!(Almanac::new(vec![1], maps).is_err());
+    }
+
+    #[test]
+    fn cyclic_chain() {
+        let maps = vec![
+            empty_mapping("seed", "soil"),
+            empty_mapping("soil", "seed"),
+            empty_mapping("water", "light"),
+        ];
+        assert!(Almanac::new(vec![1], maps).is_err());
+    }
+
+    #[test]
+    fn trace_example_seed() {
+        let almanac = example_almanac();
+        let trace = almanac.trace_id(79).unwrap();
+        assert_eq!(
+            trace,
+            vec![
+                ("seed".to_string(), 79),
+                ("soil".to_string(), 81),
+                ("fertilizer".to_string(), 81),
+                ("water".to_string(), 81),
+                ("light".to_string(), 74),
+                ("temperature".to_string(), 78),
+                ("humidity".to_string(), 78),
+                ("location".to_string(), 82),
+            ]
+        );
+    }
+
+    #[test]
+    fn map_ranges_example() {
+        let almanac = example_almanac();
+        let ranges = almanac.map_ranges().unwrap();
+        assert_eq!(ranges.iter().map(|range| range.start).min(), Some(46));
+    }
+
+    #[test]
+    fn map_ranges_split_by_last_mapping() {
+        let maps = vec![
+            empty_mapping("seed", "soil"),
+            Mapping {
+                from: "soil".into(),
+                to: "location".into(),
+                ranges: vec![MappingRange::new(100, 5, 5)],
+            },
+        ];
+        let almanac = Almanac::new(vec![3, 10], maps).unwrap();
+        let ranges = almanac.map_ranges().unwrap();
+        assert_eq!(ranges, vec![3..5, 10..13, 100..105]);
+        assert_eq!(ranges.iter().map(|range| range.start).min(), Some(3));
+    }
+
+    #[test]
+    fn map_ids_par_matches_sequential() {
+        let almanac = example_almanac();
+        assert_eq!(almanac.map_ids_par().unwrap(), almanac.map_ids().unwrap());
+    }
+
+    #[test]
+    fn seeds_across_multiple_lines() {
+        let input = format!("seeds: 79 14\n55 13\n\n{EXAMPLE_MAPS}");
+        let lines: AocStringIter = Box::new(input.lines().map(String::from));
+        let almanac = Almanac::try_from(lines).unwrap();
+        assert_eq!(almanac.start_ids, vec![79, 14, 55, 13]);
+        assert_eq!(almanac.map_ids().unwrap(), vec![82, 43, 86, 35]);
+    }
+
+    #[test]
+    fn seeds_on_a_single_line() {
+        let input = format!("seeds: 79 14 55 13\n\n{EXAMPLE_MAPS}");
+        let lines: AocStringIter = Box::new(input.lines().map(String::from));
+        let almanac = Almanac::try_from(lines).unwrap();
+        assert_eq!(almanac.start_ids, vec![79, 14, 55, 13]);
+    }
+}
@@ -1,77 +1,284 @@
 use std::path::PathBuf;
 
-use crate::BoxedError;
+use crate::{math::checked_product, BoxedError};
 use aoc_framework::{traits::*, AocSolution, AocStringIter, AocTask};
 use winnow::{
-    ascii::{digit1, multispace0, multispace1},
-    combinator::{alt, separated},
+    ascii::{alpha1, digit1, multispace0, multispace1},
+    combinator::{delimited, opt, separated, terminated},
     error::{ErrMode, ErrorKind, FromExternalError},
     PResult, Parser,
 };
 
 pub struct Day06;
 
+// The colon after the label is optional, and any whitespace (including tabs) can surround it
 fn parse_prefix<'s>(input: &mut &'s str) -> PResult<&'s str> {
-    alt(("Time:", "Distance:")).parse_next(input)?;
-    multispace0.parse_next(input)
+    let label = delimited(multispace0, alpha1, opt(':')).parse_next(input)?;
+    multispace0.parse_next(input)?;
+    Ok(label)
 }
 
-fn parse_data_separated(input: &mut &str) -> PResult<Vec<i64>> {
-    parse_prefix.parse_next(input)?;
-    separated(1.., digit1.parse_to::<i64>(), multispace1).parse_next(input)
+fn parse_data_separated<'s>(input: &mut &'s str) -> PResult<(&'s str, Vec<i128>)> {
+    let label = parse_prefix.parse_next(input)?;
+    let data = terminated(
+        separated(1.., digit1.parse_to::<i128>(), multispace1),
+        multispace0,
+    )
+    .parse_next(input)?;
+    Ok((label, data))
 }
 
-fn parse_data_single(input: &mut &str) -> PResult<Vec<i64>> {
-    parse_prefix.parse_next(input)?;
-    let num: String = separated(1.., digit1, multispace1).parse_next(input)?;
-    match num.parse::<i64>() {
-        Ok(num) => Ok(vec![num]),
+fn parse_data_single<'s>(input: &mut &'s str) -> PResult<(&'s str, Vec<i128>)> {
+    let label = parse_prefix.parse_next(input)?;
+    let num: String =
+        terminated(separated(1.., digit1, multispace1), multispace0).parse_next(input)?;
+    match num.parse::<i128>() {
+        Ok(num) => Ok((label, vec![num])),
         Err(e) => Err(ErrMode::from_external_error(input, ErrorKind::Verify, e)),
     }
 }
 
-impl AocTask for Day06 {
-    fn directory(&self) -> PathBuf {
-        "tasks/day_06".into()
+// x = hold time
+// t = total time
+// d = distance to beat
+// x * (t - x) > d
+// the distance grows up until x = t / 2 and is symmetric around it, so the first winning
+// hold time can be found with a binary search over the first half, and the last one is t - first
+// i128 is used since the merged race from phase 2 can overflow i64 when multiplied
+fn winning_bounds(t: i128, d: i128) -> Option<(i128, i128)> {
+    let beats_record = |x: i128| x * (t - x) > d;
+
+    let half = t / 2;
+    if !beats_record(half) {
+        return None;
     }
 
-    fn solution(&self, mut input: AocStringIter, phase: usize) -> Result<AocSolution, BoxedError> {
-        let time_data = input.next().ok_or("Missing time data")?;
-        let distance_data = input.next().ok_or("Missing distance data")?;
+    let (mut low, mut high) = (0, half);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if beats_record(mid) {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+
+    Some((low, t - low))
+}
+
+fn count_wins_integer(t: i128, d: i128) -> i128 {
+    winning_bounds(t, d).map_or(0, |(min_hold, max_hold)| max_hold - min_hold + 1)
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RaceResult {
+    time: i128,
+    record: i128,
+    wins: i128,
+}
+
+impl Day06 {
+    // Per-race details for the separated races from phase 1
+    #[allow(dead_code)]
+    pub fn report(input: AocStringIter) -> Result<Vec<RaceResult>, BoxedError> {
+        Self::races(input, 1)
+    }
 
+    fn races(input: AocStringIter, phase: usize) -> Result<Vec<RaceResult>, BoxedError> {
         let mut parser = match phase {
             1 => parse_data_separated,
             2 => parse_data_single,
             _ => unimplemented!(),
         };
 
-        let time = parser.parse(&time_data).map_err(|e| e.to_string())?;
-        let distance = parser.parse(&distance_data).map_err(|e| e.to_string())?;
+        let mut time = None;
+        let mut distance = None;
+        for line in input.filter(|line| !line.is_empty()) {
+            let (label, data) = parser.parse(&line).map_err(|e| e.to_string())?;
+            match label {
+                "Time" | "Duration" => time = Some(data),
+                "Distance" | "Length" => distance = Some(data),
+                _ => {
+                    let expected = "Time, Duration, Distance or Length";
+                    return Err(
+                        format!("Unknown race data label: {label}, expected {expected}").into(),
+                    );
+                }
+            }
+        }
 
-        // x = hold time
-        // t = total time
-        // d = distance to beat
-        // x * (t - x) = d
-        // -x² + xt - d = 0
-        // roots will be the min and max hold times for getting the exact
-        // distance as the record, so we'll take the integers between them
+        let time = time.ok_or("Missing time data, expected a Time or Duration line")?;
+        let distance =
+            distance.ok_or("Missing distance data, expected a Distance or Length line")?;
 
-        time.into_iter()
+        Ok(time
+            .into_iter()
             .zip(distance)
-            .map(|(t, d)| {
-                let common_sqrt = ((t.pow(2) - 4 * d) as f64).sqrt();
-                let mut x1 = (-t as f64 + common_sqrt) / -2.0;
-                let mut x2 = (-t as f64 - common_sqrt) / -2.0;
-                if x2 < x1 {
-                    std::mem::swap(&mut x1, &mut x2)
-                }
-                // min = x1_floor + 1
-                let x1_floor = x1.floor() as i64;
-                // max = x2_ceil - 1
-                let x2_ceil = x2.ceil() as i64;
-                x2_ceil - x1_floor - 1
+            .map(|(time, record)| RaceResult {
+                time,
+                record,
+                wins: count_wins_integer(time, record),
             })
-            .product::<i64>()
-            .solved()
+            .collect())
+    }
+}
+
+impl AocTask for Day06 {
+    fn directory(&self) -> PathBuf {
+        "tasks/day_06".into()
+    }
+
+    fn solution(&self, input: AocStringIter, phase: usize) -> Result<AocSolution, BoxedError> {
+        checked_product(Self::races(input, phase)?.iter().map(|race| race.wins))?.solved()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use aoc_framework::{AocStringIter, AocTask};
+    use winnow::Parser;
+
+    use super::{
+        count_wins_integer, parse_data_separated, parse_data_single, parse_prefix, winning_bounds,
+        Day06, RaceResult,
+    };
+
+    #[test]
+    fn example_races() {
+        assert_eq!(count_wins_integer(7, 9), 4);
+        assert_eq!(count_wins_integer(15, 40), 8);
+        assert_eq!(count_wins_integer(71530, 940200), 71503);
+    }
+
+    #[test]
+    fn record_equal_to_exact_product() {
+        // 10 * 20 = 200, so holding for 10 or 20 only ties the record
+        assert_eq!(count_wins_integer(30, 200), 9);
+        // 3 * 3 = 9, the only candidate ties the record
+        assert_eq!(count_wins_integer(6, 9), 0);
+    }
+
+    #[test]
+    fn unbeatable_record() {
+        assert_eq!(count_wins_integer(5, 100), 0);
+    }
+
+    #[test]
+    fn merged_race_overflowing_i64() {
+        let time = parse_data_single.parse("Time: 100000 000000 000").unwrap();
+        let distance = parse_data_single
+            .parse("Distance: 999999 999999 999")
+            .unwrap();
+        assert_eq!(time, ("Time", vec![100_000_000_000_000]));
+        assert_eq!(distance, ("Distance", vec![999_999_999_999_999]));
+        assert_eq!(
+            count_wins_integer(time.1[0], distance.1[0]),
+            99_999_999_999_979
+        );
+    }
+
+    #[test]
+    fn winning_bounds_example() {
+        assert_eq!(winning_bounds(7, 9), Some((2, 5)));
+        assert_eq!(winning_bounds(30, 200), Some((11, 19)));
+        assert_eq!(winning_bounds(6, 9), None);
+    }
+
+    #[test]
+    fn report_example() {
+        let input: AocStringIter = Box::new(
+            ["Time:      7  15   30", "Distance:  9  40  200"]
+                .into_iter()
+                .map(String::from),
+        );
+        let race = |time, record, wins| RaceResult { time, record, wins };
+        assert_eq!(
+            Day06::report(input).unwrap(),
+            vec![race(7, 9, 4), race(15, 40, 8), race(30, 200, 9)]
+        );
+    }
+
+    #[test]
+    fn arbitrary_labels() {
+        assert_eq!(parse_prefix.parse("Duration: ").unwrap(), "Duration");
+        assert_eq!(parse_prefix.parse("Length:").unwrap(), "Length");
+
+        let race = |time, record, wins| RaceResult { time, record, wins };
+        // The labels decide which row is which, not the order of the rows
+        for rows in [
+            ["Duration:  7  15   30", "Length:  9  40  200"],
+            ["Length:  9  40  200", "Duration:  7  15   30"],
+            ["Distance:  9  40  200", "Time:  7  15   30"],
+        ] {
+            let input: AocStringIter = Box::new(rows.into_iter().map(String::from));
+            assert_eq!(
+                Day06::report(input).unwrap(),
+                vec![race(7, 9, 4), race(15, 40, 8), race(30, 200, 9)]
+            );
+        }
+    }
+
+    #[test]
+    fn missing_colon() {
+        assert_eq!(
+            parse_data_separated.parse("Time 7 15 30").unwrap(),
+            ("Time", vec![7, 15, 30])
+        );
+    }
+
+    #[test]
+    fn tab_separated() {
+        assert_eq!(
+            parse_data_separated
+                .parse("Distance:\t9\t40\t200\t")
+                .unwrap(),
+            ("Distance", vec![9, 40, 200])
+        );
+        assert_eq!(
+            parse_data_single.parse("\tTime\t7\t15\t30").unwrap(),
+            ("Time", vec![71530])
+        );
+    }
+
+    #[test]
+    fn missing_labels() {
+        let input: AocStringIter = Box::new(["Time: 7 15 30"].into_iter().map(String::from));
+        assert!(Day06::report(input).is_err());
+
+        let input: AocStringIter = Box::new(["Length: 9 40 200"].into_iter().map(String::from));
+        assert!(Day06::report(input).is_err());
+    }
+
+    #[test]
+    fn unknown_label() {
+        let input: AocStringIter = Box::new(
+            ["Time: 7 15 30", "Speed: 9 40 200"]
+                .into_iter()
+                .map(String::from),
+        );
+        assert_eq!(
+            Day06::report(input).err().map(|err| err.to_string()),
+            Some(
+                "Unknown race data label: Speed, expected Time, Duration, Distance or Length"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn overflowing_product() {
+        // Every race has 10^13 - 1 winning hold times, three of them don't fit into an i128
+        let input: AocStringIter = Box::new(
+            [
+                "Time: 10000000000000 10000000000000 10000000000000",
+                "Distance: 0 0 0",
+            ]
+            .into_iter()
+            .map(String::from),
+        );
+        assert_eq!(
+            Day06.solution(input, 1).err().map(|err| err.to_string()),
+            Some("Product overflowed".to_string())
+        );
     }
 }
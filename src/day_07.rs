@@ -8,10 +8,12 @@ use std::{
 use crate::BoxedError;
 use aoc_framework::{traits::*, AocSolution, AocStringIter, AocTask};
 use itertools::Itertools;
+use thiserror::Error;
 use winnow::{
-    ascii::{digit1, multispace1},
-    combinator::{empty, fail, repeat, separated_pair},
+    ascii::{digit1, line_ending, multispace0, multispace1},
+    combinator::{empty, fail, repeat, separated, separated_pair, terminated},
     dispatch,
+    error::{ErrMode, ErrorKind, FromExternalError},
     token::any,
     PResult, Parser,
 };
@@ -37,8 +39,91 @@ enum Card {
     Ace,
 }
 
+// Decouples the order of cards from the derived Ord on Card
+#[derive(Debug, Clone, Copy)]
+struct CardRanking {
+    // Rank of every card, indexed by the card itself
+    ranks: [usize; 15],
+}
+
+impl CardRanking {
+    const DEFAULT: Self = Self::new([
+        Card::Joker,
+        Card::One,
+        Card::Two,
+        Card::Three,
+        Card::Four,
+        Card::Five,
+        Card::Six,
+        Card::Seven,
+        Card::Eight,
+        Card::Nine,
+        Card::Ten,
+        Card::Jack,
+        Card::Queen,
+        Card::King,
+        Card::Ace,
+    ]);
+
+    #[allow(dead_code)]
+    const ACES_LOW: Self = Self::new([
+        Card::Joker,
+        Card::Ace,
+        Card::One,
+        Card::Two,
+        Card::Three,
+        Card::Four,
+        Card::Five,
+        Card::Six,
+        Card::Seven,
+        Card::Eight,
+        Card::Nine,
+        Card::Ten,
+        Card::Jack,
+        Card::Queen,
+        Card::King,
+    ]);
+
+    // Takes the cards ordered from the weakest to the strongest
+    const fn new(order: [Card; 15]) -> Self {
+        let mut ranks = [usize::MAX; 15];
+        let mut rank = 0;
+        while rank < order.len() {
+            ranks[order[rank] as usize] = rank;
+            rank += 1;
+        }
+
+        let mut card = 0;
+        while card < ranks.len() {
+            assert!(
+                ranks[card] != usize::MAX,
+                "Card ranking should contain every card"
+            );
+            card += 1;
+        }
+        Self { ranks }
+    }
+
+    fn rank(&self, card: &Card) -> usize {
+        self.ranks[*card as usize]
+    }
+
+    fn cmp_cards(&self, cards: &[Card], other: &[Card]) -> Ordering {
+        cards
+            .iter()
+            .map(|card| self.rank(card))
+            .cmp(other.iter().map(|card| self.rank(card)))
+    }
+}
+
+impl Default for CardRanking {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
-enum HandType {
+pub(crate) enum HandType {
     HighCard,
     Pair,
     TwoPair,
@@ -49,31 +134,32 @@ enum HandType {
 }
 
 #[derive(Debug)]
-struct Bid {
+pub(crate) struct Bid {
     hand: Hand,
     bid: u32,
 }
 
 #[derive(Debug, Clone)]
-struct Hand(Vec<Card>);
+pub(crate) struct Hand(Vec<Card>);
 
 impl Hand {
-    fn eval(&self) -> HandType {
+    pub(crate) fn eval(&self) -> HandType {
         let counts_by_card = self.0.iter().counts();
         let mut counts = counts_by_card.values().sorted_by(|a, b| b.cmp(a));
         match (counts.next(), counts.next()) {
             (Some(1), _) => HandType::HighCard,
             (Some(2), Some(2)) => HandType::TwoPair,
             (Some(2), _) => HandType::Pair,
-            (Some(3), Some(2)) => HandType::FullHouse,
+            (Some(3), Some(2..)) => HandType::FullHouse,
             (Some(3), _) => HandType::Threes,
             (Some(4), _) => HandType::Fours,
-            (Some(5), None) => HandType::Fives,
+            (Some(5..), _) => HandType::Fives,
             _ => unreachable!("{self:?}"),
         }
     }
 
     fn with_joker(&self) -> Hand {
+        let hand_size = self.0.len();
         let mut non_jokers: Vec<Card> = self
             .0
             .iter()
@@ -87,25 +173,82 @@ impl Hand {
             .into_iter()
             .max_by(|a, b| a.1.cmp(&b.1))
             .map(|(card, count)| *card)
-            // If the hand has no regular cards, replace it with 5 aces
+            // If the hand has no regular cards, replace it with aces
             .unwrap_or(Card::Ace);
 
         // Extend non_jokers with the most common card to build the strongest hand
-        non_jokers.extend(repeat_iter(most_common_card).take(5 - non_jokers.len()));
+        non_jokers.extend(repeat_iter(most_common_card).take(hand_size - non_jokers.len()));
         Hand(non_jokers)
     }
 
-    fn eval_with_joker(&self) -> HandType {
+    // Replaces the jokers in place with the most common card, preferring higher cards on ties
+    #[allow(dead_code)]
+    fn best_joker_hand(&self) -> Hand {
+        let best_card = self
+            .0
+            .iter()
+            .filter(|card| **card != Card::Joker)
+            .counts()
+            .into_iter()
+            .max_by_key(|(card, count)| (*count, **card))
+            .map(|(card, count)| *card)
+            // If the hand has no regular cards, replace it with aces
+            .unwrap_or(Card::Ace);
+
+        let best_hand = self
+            .0
+            .iter()
+            .map(|card| {
+                if *card == Card::Joker {
+                    best_card
+                } else {
+                    *card
+                }
+            })
+            .collect();
+        Hand(best_hand)
+    }
+
+    pub(crate) fn eval_with_joker(&self) -> HandType {
         self.with_joker().eval()
     }
 
+    // Only used to check the phase 2 sort keys
+    #[allow(dead_code)]
     fn cmp_with_joker(&self, other: &Self) -> Ordering {
+        self.cmp_with_joker_ranked(other, &CardRanking::DEFAULT)
+    }
+
+    fn cmp_with_joker_ranked(&self, other: &Self, ranking: &CardRanking) -> Ordering {
         match self.eval_with_joker().partial_cmp(&other.eval_with_joker()) {
-            Some(Equal) => self.0.cmp(&other.0),
+            Some(Equal) => ranking.cmp_cards(&self.0, &other.0),
             ord => ord.expect("Invalid ordering"),
         }
     }
 
+    fn cmp_ranked(&self, other: &Self, ranking: &CardRanking) -> Ordering {
+        match self.eval().cmp(&other.eval()) {
+            Equal => ranking.cmp_cards(&self.0, &other.0),
+            ord => ord,
+        }
+    }
+
+    // Also returns the index of the first differing card, which decides the order of hands with
+    // equal types. Hands of different types are ordered by the type alone, so there is no index
+    #[allow(dead_code)]
+    fn cmp_explain(&self, other: &Self) -> (Ordering, Option<usize>) {
+        if self.eval() != other.eval() {
+            return (self.cmp(other), None);
+        }
+        let first_difference = self
+            .0
+            .iter()
+            .zip(&other.0)
+            .position(|(card, other_card)| card != other_card)
+            .or((self.0.len() != other.0.len()).then_some(self.0.len().min(other.0.len())));
+        (self.cmp(other), first_difference)
+    }
+
     fn jacks_as_jokers(&self) -> Self {
         let joker_hand = self
             .0
@@ -137,16 +280,33 @@ impl PartialOrd for Hand {
 impl Eq for Hand {}
 impl Ord for Hand {
     fn cmp(&self, other: &Self) -> Ordering {
-        match self.eval().cmp(&other.eval()) {
-            Equal => self.0.cmp(&other.0),
-            ord => ord,
-        }
+        self.cmp_ranked(other, &CardRanking::DEFAULT)
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("card '{0}' is not valid in Camel Cards")]
+struct InvalidCardError(char);
+
+fn invalid_card(card: char) -> impl FnMut(&mut &str) -> PResult<Card> {
+    move |input: &mut &str| {
+        Err(ErrMode::from_external_error(input, ErrorKind::Verify, InvalidCardError(card)).cut())
+    }
+}
+
+// Ones are only valid in the variant enabled by the card-one feature
+fn parse_card_one(input: &mut &str) -> PResult<Card> {
+    if cfg!(feature = "card-one") {
+        Ok(Card::One)
+    } else {
+        invalid_card('1').parse_next(input)
     }
 }
 
 fn parse_card(input: &mut &str) -> PResult<Card> {
     dispatch!(any;
-        '1' => empty.value(Card::One),
+        '0' => invalid_card('0'),
+        '1' => parse_card_one,
         '2' => empty.value(Card::Two),
         '3' => empty.value(Card::Three),
         '4' => empty.value(Card::Four),
@@ -165,8 +325,9 @@ fn parse_card(input: &mut &str) -> PResult<Card> {
     .parse_next(input)
 }
 
+// The hand size is derived from the number of parsed cards
 fn parse_hand(input: &mut &str) -> PResult<Hand> {
-    Ok(Hand(repeat(5, parse_card).parse_next(input)?))
+    Ok(Hand(repeat(1.., parse_card).parse_next(input)?))
 }
 
 fn parse_bid(input: &mut &str) -> PResult<Bid> {
@@ -175,6 +336,50 @@ fn parse_bid(input: &mut &str) -> PResult<Bid> {
     Ok(Bid { hand, bid })
 }
 
+// Parses a whole bids document at once, trailing whitespace is allowed
+#[allow(dead_code)]
+pub(crate) fn parse_bids(input: &mut &str) -> PResult<Vec<Bid>> {
+    terminated(separated(1.., parse_bid, line_ending), multispace0).parse_next(input)
+}
+
+impl Day07 {
+    // Pairs every hand from the input with its type, without jokers
+    #[allow(dead_code)]
+    pub(crate) fn classify(input: AocStringIter) -> Result<Vec<(Hand, HandType)>, BoxedError> {
+        Ok(input
+            .map(|line| parse_bid.parse(&line).map_err(|e| e.to_string()))
+            .map_ok(|bid| {
+                let hand_type = bid.hand.eval();
+                (bid.hand, hand_type)
+            })
+            .collect::<Result<Vec<_>, _>>()?)
+    }
+
+    // Sorts the bids in place, the hands themselves are left untouched
+    pub(crate) fn total_winnings(bids: &mut [Bid], phase: usize) -> u32 {
+        match phase {
+            1 => bids.sort_by(|a, b| a.hand.cmp(&b.hand)),
+            // Orders the hands like cmp_with_joker would after turning the jacks into jokers,
+            // each hand is only converted once instead of on every comparison
+            2 => bids.sort_by_cached_key(|bid| {
+                let hand = bid.hand.jacks_as_jokers();
+                let ranks: Vec<_> = hand
+                    .0
+                    .iter()
+                    .map(|card| CardRanking::DEFAULT.rank(card))
+                    .collect();
+                (hand.eval_with_joker(), ranks)
+            }),
+            _ => unimplemented!(),
+        }
+
+        bids.iter()
+            .enumerate()
+            .map(|(i, bid)| bid.bid * (i as u32 + 1))
+            .sum()
+    }
+}
+
 impl AocTask for Day07 {
     fn directory(&self) -> PathBuf {
         "tasks/day_07".into()
@@ -185,20 +390,169 @@ impl AocTask for Day07 {
             .map(|line| parse_bid.parse(&line).map_err(|e| e.to_string()))
             .collect::<Result<Vec<_>, _>>()?;
 
-        match phase {
-            1 => bids.iter_mut().sorted_by(|a, b| a.hand.cmp(&b.hand)),
-            2 => bids
-                .iter_mut()
-                .map(|bid| {
-                    bid.hand = bid.hand.jacks_as_jokers();
-                    bid
-                })
-                .sorted_by(|a, b| a.hand.cmp_with_joker(&b.hand)),
-            _ => unimplemented!(),
-        }
-        .enumerate()
-        .map(|(i, bid)| bid.bid * (i as u32 + 1))
-        .sum::<u32>()
-        .solved()
+        Self::total_winnings(&mut bids, phase).solved()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cmp::Ordering::{Equal, Greater, Less};
+
+    use winnow::Parser;
+
+    use aoc_framework::AocStringIter;
+
+    use super::{parse_bid, parse_bids, parse_hand, CardRanking, Day07, HandType};
+
+    #[test]
+    fn aces_low_ranking() {
+        let aces = parse_hand.parse("AKQT9").unwrap();
+        let kings = parse_hand.parse("KQT98").unwrap();
+
+        assert_eq!(aces.cmp_ranked(&kings, &CardRanking::default()), Greater);
+        assert_eq!(aces.cmp_ranked(&kings, &CardRanking::ACES_LOW), Less);
+        assert_eq!(
+            aces.cmp_with_joker_ranked(&kings, &CardRanking::ACES_LOW),
+            Less
+        );
+    }
+
+    #[test]
+    fn seven_card_fives() {
+        let hand = parse_hand.parse("AAAAA23").unwrap();
+        assert_eq!(hand.0.len(), 7);
+        assert_eq!(hand.eval(), HandType::Fives);
+
+        let bid = parse_bid.parse("AAJJJ23 100").unwrap();
+        let with_joker = bid.hand.jacks_as_jokers().with_joker();
+        assert_eq!(with_joker.0.len(), 7);
+        assert_eq!(with_joker.eval(), HandType::Fives);
+    }
+
+    #[test]
+    fn three_card_hand() {
+        let hand = parse_hand.parse("KK2").unwrap();
+        assert_eq!(hand.eval(), HandType::Pair);
+        assert_eq!(hand.jacks_as_jokers().eval_with_joker(), HandType::Pair);
+    }
+
+    #[test]
+    fn classify_hands() {
+        let input: AocStringIter =
+            Box::new(["32T3K 765", "QQQJA 483"].into_iter().map(String::from));
+        let classified = Day07::classify(input).unwrap();
+        let types: Vec<_> = classified.iter().map(|(_, hand_type)| hand_type).collect();
+        assert_eq!(types, vec![&HandType::Pair, &HandType::Threes]);
+
+        let (hand, _) = &classified[1];
+        assert_eq!(hand.jacks_as_jokers().eval_with_joker(), HandType::Fours);
+    }
+
+    #[test]
+    fn best_joker_hand_prefers_higher_cards() {
+        let hand = parse_hand.parse("JJ234").unwrap().jacks_as_jokers();
+        let best_hand = hand.best_joker_hand();
+        assert_eq!(best_hand, parse_hand.parse("44234").unwrap());
+        assert_eq!(best_hand.eval(), HandType::Threes);
+
+        let all_jokers = parse_hand.parse("JJJJJ").unwrap().jacks_as_jokers();
+        assert_eq!(
+            all_jokers.best_joker_hand(),
+            parse_hand.parse("AAAAA").unwrap()
+        );
+    }
+
+    #[test]
+    fn total_winnings_example() {
+        let bids = || {
+            [
+                "32T3K 765",
+                "T55J5 684",
+                "KK677 28",
+                "KTJJT 220",
+                "QQQJA 483",
+            ]
+            .into_iter()
+            .map(|line| parse_bid.parse(line).unwrap())
+            .collect::<Vec<_>>()
+        };
+
+        assert_eq!(Day07::total_winnings(&mut bids(), 1), 6440);
+        assert_eq!(Day07::total_winnings(&mut bids(), 2), 5905);
+
+        // The same bids can be ranked again, in either phase
+        let mut reused = bids();
+        assert_eq!(Day07::total_winnings(&mut reused, 2), 5905);
+        assert_eq!(Day07::total_winnings(&mut reused, 2), 5905);
+        assert_eq!(Day07::total_winnings(&mut reused, 1), 6440);
+
+        // The cached sort keys order the hands the same way as cmp_with_joker
+        let mut ranked = bids();
+        Day07::total_winnings(&mut ranked, 2);
+        let mut compared: Vec<_> = bids()
+            .into_iter()
+            .map(|bid| bid.hand.jacks_as_jokers())
+            .collect();
+        compared.sort_by(|a, b| a.cmp_with_joker(b));
+        assert_eq!(
+            ranked
+                .iter()
+                .map(|bid| bid.hand.jacks_as_jokers().0)
+                .collect::<Vec<_>>(),
+            compared.into_iter().map(|hand| hand.0).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn parse_bids_document() {
+        let document = "32T3K 765
+T55J5 684
+KK677 28
+KTJJT 220
+QQQJA 483
+";
+        let bids = || parse_bids.parse(document).unwrap();
+        assert_eq!(bids().len(), 5);
+        assert_eq!(bids()[3].bid, 220);
+        assert_eq!(bids()[3].hand, parse_hand.parse("KTJJT").unwrap());
+
+        assert_eq!(Day07::total_winnings(&mut bids(), 1), 6440);
+        assert_eq!(Day07::total_winnings(&mut bids(), 2), 5905);
+    }
+
+    #[test]
+    #[cfg(not(feature = "card-one"))]
+    fn invalid_card_one() {
+        let error = parse_hand.parse("1234A").unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("card '1' is not valid in Camel Cards"));
+    }
+
+    #[test]
+    fn invalid_card_zero() {
+        let error = parse_bid.parse("2340A 100").unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("card '0' is not valid in Camel Cards"));
+    }
+
+    #[test]
+    fn cmp_explain_full_houses() {
+        let threes = parse_hand.parse("33322").unwrap();
+        let twos = parse_hand.parse("22233").unwrap();
+        assert_eq!(threes.eval(), HandType::FullHouse);
+        assert_eq!(twos.eval(), HandType::FullHouse);
+        assert_eq!(threes.cmp_explain(&twos), (Greater, Some(0)));
+        assert_eq!(twos.cmp_explain(&threes), (Less, Some(0)));
+        assert_eq!(threes.cmp_explain(&threes), (Equal, None));
+    }
+
+    #[test]
+    fn cmp_explain_different_types() {
+        let two_pair = parse_hand.parse("KTJJT").unwrap();
+        let pair = parse_hand.parse("32T3K").unwrap();
+        assert_eq!(two_pair.cmp_explain(&pair), (Greater, None));
+        assert_eq!(pair.cmp_explain(&two_pair), (Less, None));
     }
 }
@@ -1,10 +1,11 @@
-use std::{collections::HashMap, iter::once, path::PathBuf};
+use std::{collections::HashMap, path::PathBuf};
 
-use crate::BoxedError;
+use crate::{context::Context, math::lcm_many, BoxedError};
 use aoc_framework::{traits::*, AocSolution, AocStringIter, AocTask};
+use itertools::Itertools;
 use winnow::{
-    ascii::{alphanumeric1, multispace0},
-    combinator::{delimited, empty, fail, repeat, separated_pair},
+    ascii::{alphanumeric1, line_ending, multispace0},
+    combinator::{delimited, empty, fail, repeat, separated, separated_pair},
     dispatch,
     token::any,
     PResult, Parser,
@@ -18,7 +19,19 @@ enum Instruction {
     Right,
 }
 
-struct Path(Box<dyn Iterator<Item = Instruction>>);
+#[derive(Debug, Clone)]
+struct Path(Vec<Instruction>);
+
+impl Path {
+    // Replays the instructions in a cycle
+    fn iter(&self) -> impl Iterator<Item = &Instruction> {
+        self.0.iter().cycle()
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
 
 type NodeDirections = (String, String);
 type Node = (String, NodeDirections);
@@ -35,41 +48,164 @@ impl Map {
         }
     }
 
+    fn follow_one(&self, path: &Path, start: &str, end: &str) -> Option<usize> {
+        // Every (node, instruction) state has been visited after this many steps
+        let max_steps = self.nodes.len() * path.len();
+
+        let mut instructions = path.iter();
+        let mut current_node = start;
+        for steps in 1..=max_steps {
+            let directions = self.nodes.get(current_node)?;
+            current_node = match instructions.next()? {
+                Instruction::Left => directions.0.as_str(),
+                Instruction::Right => directions.1.as_str(),
+            };
+            if current_node == end {
+                return Some(steps);
+            }
+        }
+        None
+    }
+
+    // Returns the (offset, period) pair of every ghost, where the offset is the number of steps
+    // until the first target node, and the period is the number of steps between target hits.
+    // Ghosts keep walking until the (node, instruction) state of their first target recurs,
+    // which makes sure the cycles are actually periodic, and every hit in between has to be
+    // evenly spaced for the period to describe all of them.
     fn follow_many(
         &self,
-        mut path: Path,
+        path: &Path,
         start_char: char,
         target_char: char,
-    ) -> Option<Vec<usize>> {
+    ) -> Result<Vec<(usize, usize)>, BoxedError> {
         let mut current_nodes: Vec<_> = self
             .nodes
             .keys()
             .filter(|location| location.ends_with(start_char))
+            .map(|location| Ghost {
+                start: location,
+                node: location,
+                first_hit: None,
+                last_hit: 0,
+                gap: None,
+            })
             .collect();
 
+        // Every (node, instruction) state has been visited after this many steps,
+        // so the target can't be reached (or revisited) if it wasn't found by then
+        let max_steps = self.nodes.len() * path.len();
+
+        let mut instructions = path.iter();
         let mut next_nodes = vec![];
         let mut cycles = vec![];
         let mut steps = 0;
         while !current_nodes.is_empty() {
             steps += 1;
-            let step = path.0.next()?;
-            while !current_nodes.is_empty() {
-                let current_node = current_nodes.pop()?;
-                let directions = self.nodes.get(current_node)?;
-                let next_node = match step {
+            let step = instructions.next().context("Path is empty")?;
+            while let Some(mut ghost) = current_nodes.pop() {
+                match ghost.first_hit {
+                    None if steps > max_steps => {
+                        return Err(format!(
+                            "Couldn't reach a node ending with {target_char} from: {}",
+                            ghost.start
+                        )
+                        .into())
+                    }
+                    Some((offset, node, _)) if steps - offset > max_steps => {
+                        return Err(format!(
+                            "The path from {} is aperiodic, {node} is never revisited",
+                            ghost.start
+                        )
+                        .into())
+                    }
+                    _ => {}
+                }
+
+                let directions = self
+                    .nodes
+                    .get(ghost.node)
+                    .ok_or_else(|| format!("Missing node: {}", ghost.node))?;
+                ghost.node = match step {
                     Instruction::Left => &directions.0,
                     Instruction::Right => &directions.1,
                 };
-                if next_node.ends_with(target_char) {
-                    cycles.push(steps);
-                } else {
-                    next_nodes.push(next_node);
+
+                if ghost.node.ends_with(target_char) {
+                    let state = (ghost.node, steps % path.len());
+                    match ghost.first_hit {
+                        None => ghost.first_hit = Some((steps, state.0, state.1)),
+                        Some((offset, node, instruction)) => {
+                            // A single (offset, period) pair only describes evenly spaced hits
+                            let gap = steps - ghost.last_hit;
+                            if *ghost.gap.get_or_insert(gap) != gap {
+                                return Err(format!(
+                                    "The cycle from {} reaches its targets at irregular intervals",
+                                    ghost.start
+                                )
+                                .into());
+                            }
+                            if (node, instruction) == state {
+                                cycles.push((offset, gap));
+                                continue;
+                            }
+                        }
+                    }
+                    ghost.last_hit = steps;
                 }
+                next_nodes.push(ghost);
             }
             std::mem::swap(&mut next_nodes, &mut current_nodes);
         }
-        Some(cycles)
+        Ok(cycles)
+    }
+}
+
+// A ghost walking the map, along with the step count, node and instruction index of the first
+// target it reached, and the spacing of the targets it reached since
+struct Ghost<'m> {
+    start: &'m String,
+    node: &'m String,
+    first_hit: Option<(usize, &'m String, usize)>,
+    last_hit: usize,
+    gap: Option<usize>,
+}
+
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (gcd, x, y) = extended_gcd(b, a % b);
+        (gcd, y, x - (a / b) * y)
+    }
+}
+
+// Finds the first step at which all cycles hit their target at the same time, using the
+// Chinese Remainder Theorem generalized to moduli which aren't pairwise coprime
+fn combine_cycles(cycles: &[(usize, usize)]) -> Option<usize> {
+    if cycles.iter().all(|(offset, period)| offset == period) {
+        return Some(lcm_many(cycles.iter().map(|(_, period)| *period)));
+    }
+
+    let (mut residue, mut modulus) = (0i128, 1i128);
+    for (offset, period) in cycles {
+        let (offset, period) = (*offset as i128, *period as i128);
+        let (gcd, x, _) = extended_gcd(modulus, period);
+        let difference = offset - residue;
+        if difference % gcd != 0 {
+            return None;
+        }
+
+        let lcm = modulus / gcd * period;
+        residue = (residue + modulus * (difference / gcd * x % (period / gcd))).rem_euclid(lcm);
+        modulus = lcm;
+    }
+
+    // The solution has to come after every ghost reaches its first target
+    let max_offset = cycles.iter().map(|(offset, _)| *offset).max()? as i128;
+    if residue < max_offset {
+        residue += (max_offset - residue + modulus - 1) / modulus * modulus;
     }
+    Some(residue as usize)
 }
 
 fn parse_path(input: &mut &str) -> PResult<Path> {
@@ -82,7 +218,7 @@ fn parse_path(input: &mut &str) -> PResult<Path> {
         ),
     )
     .parse_next(input)?;
-    Ok(Path(Box::new(instructions.into_iter().cycle())))
+    Ok(Path(instructions))
 }
 
 fn parse_node(input: &mut &str) -> PResult<Node> {
@@ -102,140 +238,223 @@ fn parse_node(input: &mut &str) -> PResult<Node> {
     .parse_next(input)
 }
 
-trait Lcm
-where
-    Self: Sized,
-{
-    fn prime_factors(&self) -> HashMap<Self, Self>;
-    #[allow(dead_code)]
-    fn lcm(&self, other: &Self) -> Self;
+fn parse_map(input: &mut &str) -> PResult<(Path, Map)> {
+    let path = parse_path.parse_next(input)?;
+    (line_ending, line_ending).parse_next(input)?;
+    let nodes = separated(1.., parse_node, line_ending).parse_next(input)?;
+    multispace0.parse_next(input)?;
+    Ok((path, Map::new(nodes)))
 }
 
-impl Lcm for usize {
-    fn prime_factors(&self) -> HashMap<Self, Self> {
-        assert_ne!(self, &0usize);
-
-        let mut factors = HashMap::new();
-        let mut number = *self;
-
-        for factor in once(2).chain((3..).step_by(2)) {
-            while number % factor == 0 {
-                factors
-                    .entry(factor)
-                    .and_modify(|power| *power *= factor)
-                    .or_insert(factor);
-                number /= factor;
-            }
-
-            if number == 1 {
-                break;
-            }
-        }
-
-        factors
+impl AocTask for Day08 {
+    fn directory(&self) -> PathBuf {
+        "tasks/day_08".into()
     }
 
-    fn lcm(&self, other: &Self) -> Self {
-        if self == &0usize && self == other {
-            0
-        } else {
-            [*self, *other].lcm()
+    fn solution(&self, mut input: AocStringIter, phase: usize) -> Result<AocSolution, BoxedError> {
+        let document = input.join("\n");
+        let (path, map) = parse_map.parse(&document).context("parsing the map")?;
+
+        match phase {
+            1 => map
+                .follow_one(&path, "AAA", "ZZZ")
+                .context("Couldn't reach ZZZ from AAA")?
+                .solved(),
+            2 => combine_cycles(&map.follow_many(&path, 'A', 'Z')?)
+                .context("The ghost cycles never align")?
+                .solved(),
+            _ => unimplemented!(),
         }
     }
 }
 
-trait LcmMany<L>
-where
-    L: Lcm,
-{
-    fn lcm(&self) -> L;
-}
+#[cfg(test)]
+mod phase_1 {
+    use winnow::Parser;
 
-impl<I> LcmMany<usize> for I
-where
-    for<'a> &'a I: IntoIterator<Item = &'a usize>,
-{
-    fn lcm(&self) -> usize {
-        let highest_power_factors = self
-            .into_iter()
-            .flat_map(|number| number.prime_factors())
-            .fold(
-                HashMap::<usize, usize>::new(),
-                |mut acc, (factor, power)| {
-                    acc.entry(factor)
-                        .and_modify(|max_power| *max_power = power.max(*max_power))
-                        .or_insert(power);
-                    acc
-                },
-            );
-        highest_power_factors.values().product()
+    use super::{parse_map, parse_node, parse_path, Map};
+
+    fn map(nodes: &[&str]) -> Map {
+        Map::new(
+            nodes
+                .iter()
+                .map(|line| parse_node.parse(line).unwrap())
+                .collect(),
+        )
     }
-}
 
-impl AocTask for Day08 {
-    fn directory(&self) -> PathBuf {
-        "tasks/day_08".into()
+    #[test]
+    fn follow_one_repeating_path() {
+        let path = parse_path.parse("LLR").unwrap();
+        let map = map(&["AAA = (BBB, BBB)", "BBB = (AAA, ZZZ)", "ZZZ = (ZZZ, ZZZ)"]);
+        assert_eq!(map.follow_one(&path, "AAA", "ZZZ"), Some(6));
     }
 
-    fn solution(&self, mut input: AocStringIter, phase: usize) -> Result<AocSolution, BoxedError> {
-        let path = input
-            .next()
-            .map(|line| parse_path.parse(&line).map_err(|e| e.to_string()))
-            .ok_or("Missing path")??;
+    #[test]
+    fn follow_one_branching_map() {
+        let path = parse_path.parse("RL").unwrap();
+        let map = map(&[
+            "AAA = (BBB, CCC)",
+            "BBB = (DDD, EEE)",
+            "CCC = (ZZZ, GGG)",
+            "DDD = (DDD, DDD)",
+            "EEE = (EEE, EEE)",
+            "GGG = (GGG, GGG)",
+            "ZZZ = (ZZZ, ZZZ)",
+        ]);
+        assert_eq!(map.follow_one(&path, "AAA", "ZZZ"), Some(2));
+    }
 
-        let map = Map::new(
-            input
-                .skip_while(|line| line.is_empty())
-                .map(|line| parse_node.parse(&line).map_err(|e| e.to_string()))
-                .collect::<Result<Vec<_>, _>>()?,
-        );
+    #[test]
+    fn follow_one_unreachable() {
+        let path = parse_path.parse("L").unwrap();
+        let map = map(&["AAA = (AAA, ZZZ)", "ZZZ = (ZZZ, ZZZ)"]);
+        assert_eq!(map.follow_one(&path, "AAA", "ZZZ"), None);
+    }
 
-        match phase {
-            1 | 2 => map
-                .follow_many(path, 'A', 'Z')
-                .ok_or("Invalid path")?
-                .lcm()
-                .solved(),
-            _ => unimplemented!(),
-        }
+    #[test]
+    fn parse_full_document() {
+        let document = "LLR
+
+AAA = (BBB, BBB)
+BBB = (AAA, ZZZ)
+ZZZ = (ZZZ, ZZZ)
+";
+        let (path, map) = parse_map.parse(document).unwrap();
+        assert_eq!(path.len(), 3);
+        assert_eq!(map.nodes.len(), 3);
+        assert_eq!(map.follow_one(&path, "AAA", "ZZZ"), Some(6));
     }
 }
 
 #[cfg(test)]
 mod phase_2 {
+    use aoc_framework::AocTask;
+    use itertools::Itertools;
     use winnow::Parser;
 
-    use super::{parse_node, parse_path, LcmMany, Map};
+    use super::{combine_cycles, lcm_many, parse_map, parse_node, parse_path, Day08, Map};
+    use crate::input::sample;
 
     #[test]
-    fn lcm() {
-        assert_eq!(vec![8, 9, 21].lcm(), 504)
+    fn phase_specific_example() {
+        let document = sample(8).join("\n");
+        let (path, map) = parse_map.parse(&document).unwrap();
+        let paths_to_cycles = map.follow_many(&path, 'A', 'Z').unwrap();
+        assert_eq!(paths_to_cycles, vec![(2, 2), (3, 3)]);
+
+        let result = combine_cycles(&paths_to_cycles);
+        assert_eq!(result, Some(6));
     }
 
     #[test]
-    fn phase_specific_example() {
-        let header = "LR";
-        let nodes = "11A = (11B, XXX)
-11B = (XXX, 11Z)
-11Z = (11B, XXX)
-22A = (22B, XXX)
-22B = (22C, 22C)
-22C = (22Z, 22Z)
-22Z = (22B, 22B)
-XXX = (XXX, XXX)";
-
-        let path = parse_path.parse(header).unwrap();
+    fn sample_solution() {
+        assert_eq!(Day08.solution(sample(8), 2).unwrap().to_string(), "6");
+    }
+
+    #[test]
+    fn unreachable_target() {
+        let path = parse_path.parse("LR").unwrap();
         let map = Map::new(
-            nodes
-                .split('\n')
-                .skip_while(|line| line.is_empty())
+            [
+                "11A = (11B, 11B)",
+                "11B = (11A, 11A)",
+                "22A = (22Z, 22Z)",
+                "22Z = (22Z, 22Z)",
+            ]
+            .into_iter()
+            .map(|line| parse_node.parse(line).unwrap())
+            .collect(),
+        );
+        let error = map.follow_many(&path, 'A', 'Z').unwrap_err();
+        assert!(error.to_string().contains("11A"));
+    }
+
+    #[test]
+    fn combine_mismatched_offsets() {
+        assert_eq!(combine_cycles(&[(2, 3), (3, 4)]), Some(11));
+        assert_eq!(combine_cycles(&[(3, 3), (4, 4)]), Some(12));
+        assert_eq!(combine_cycles(&[(1, 2), (2, 4)]), None);
+    }
+
+    #[test]
+    fn follow_mismatched_offsets() {
+        let path = parse_path.parse("L").unwrap();
+        let map = Map::new(
+            [
+                "11A = (11B, XXX)",
+                "11B = (11Z, XXX)",
+                "11Z = (11C, XXX)",
+                "11C = (11Z, XXX)",
+                "22A = (22Z, XXX)",
+                "22Z = (22B, XXX)",
+                "22B = (22C, XXX)",
+                "22C = (22Z, XXX)",
+                "XXX = (XXX, XXX)",
+            ]
+            .into_iter()
+            .map(|line| parse_node.parse(line).unwrap())
+            .collect(),
+        );
+        let mut cycles = map.follow_many(&path, 'A', 'Z').unwrap();
+        cycles.sort();
+        assert_eq!(cycles, vec![(1, 3), (2, 2)]);
+        assert_eq!(combine_cycles(&cycles), Some(4));
+        assert_ne!(lcm_many(cycles.iter().map(|(_, period)| *period)), 4);
+    }
+
+    #[test]
+    fn irregular_targets() {
+        let path = parse_path.parse("L").unwrap();
+        let map = Map::new(
+            [
+                "11A = (11Z, XXX)",
+                "11Z = (12Z, XXX)",
+                "12Z = (11B, XXX)",
+                "11B = (11Z, XXX)",
+                "XXX = (XXX, XXX)",
+            ]
+            .into_iter()
+            .map(|line| parse_node.parse(line).unwrap())
+            .collect(),
+        );
+        let error = map.follow_many(&path, 'A', 'Z').unwrap_err();
+        assert!(error.to_string().contains("irregular"));
+    }
+
+    #[test]
+    fn aperiodic_start() {
+        let path = parse_path.parse("L").unwrap();
+        let map = Map::new(
+            ["11A = (11Z, 11Z)", "11Z = (11B, 11B)", "11B = (11B, 11B)"]
+                .into_iter()
                 .map(|line| parse_node.parse(line).unwrap())
-                .collect::<Vec<_>>(),
+                .collect(),
+        );
+        let error = map.follow_many(&path, 'A', 'Z').unwrap_err();
+        assert!(error.to_string().contains("aperiodic"));
+    }
+
+    #[test]
+    fn replay_path() {
+        let path = parse_path.parse("LR").unwrap();
+        let map = Map::new(
+            [
+                "11A = (11B, XXX)",
+                "11B = (XXX, 11Z)",
+                "11Z = (11B, XXX)",
+                "XXX = (XXX, XXX)",
+            ]
+            .into_iter()
+            .map(|line| parse_node.parse(line).unwrap())
+            .collect(),
         );
-        let paths_to_cycles = map.follow_many(path, 'A', 'Z').unwrap();
-        assert_eq!(paths_to_cycles, vec![2, 3]);
 
-        let result = paths_to_cycles.lcm();
-        assert_eq!(result, 6);
+        let first_run = map.follow_many(&path, 'A', 'Z').unwrap();
+        let second_run = map.follow_many(&path, 'A', 'Z').unwrap();
+        assert_eq!(first_run, vec![(2, 2)]);
+        assert_eq!(first_run, second_run);
+        assert_eq!(map.follow_one(&path, "11A", "11Z"), Some(2));
+        assert_eq!(map.follow_one(&path.clone(), "11A", "11Z"), Some(2));
     }
 }
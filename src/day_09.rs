@@ -1,4 +1,9 @@
-use std::{fmt::Display, iter::once, path::PathBuf};
+use std::{
+    fmt::{Debug, Display},
+    iter::once,
+    ops::{Add, Sub},
+    path::PathBuf,
+};
 
 use crate::BoxedError;
 use aoc_framework::{traits::*, AocSolution, AocStringIter, AocTask};
@@ -11,7 +16,7 @@ use winnow::{
 
 pub struct Day09;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 enum ExtrapolationKind {
     Forwards,
     Backwards,
@@ -19,25 +24,31 @@ enum ExtrapolationKind {
 
 use ExtrapolationKind::*;
 
+// Numeric types which can back a History, the default value is used as zero
+trait Element: Copy + Default + Debug + PartialEq + Add<Output = Self> + Sub<Output = Self> {}
+
+impl<T: Copy + Default + Debug + PartialEq + Add<Output = T> + Sub<Output = T>> Element for T {}
+
 #[derive(Debug, Clone)]
-struct History {
-    data: Vec<i32>,
-    derived: Vec<Vec<i32>>,
+struct History<T = i64> {
+    data: Vec<T>,
+    derived: Vec<Vec<T>>,
 }
 
-impl Display for History {
+impl<T: Element> Display for History<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "{:?}", self.data)?;
-        for row in &self.derived {
-            writeln!(f, "{:?}", row)?;
+        // Each derived row is indented further to show the triangle
+        for (depth, row) in self.derived.iter().enumerate() {
+            writeln!(f, "{:indent$}{:?}", "", row, indent = (depth + 1) * 2)?;
         }
         Ok(())
     }
 }
 
 // Unwraps in History impl are safe due to the parser restrictions (at least one record per history)
-impl History {
-    fn new(data: Vec<i32>) -> History {
+impl<T: Element> History<T> {
+    fn new(data: Vec<T>) -> Self {
         History {
             data,
             derived: vec![],
@@ -45,29 +56,31 @@ impl History {
         .derived()
     }
 
-    fn derived(mut self) -> History {
+    fn derived(mut self) -> Self {
         let mut current = &self.data;
-        while !current.iter().all_equal() {
+        // A row with a single value can't be differenced any further, it's treated as constant
+        while current.len() > 1 && !current.iter().all_equal() {
             let mut next = vec![];
             for (first, second) in current.iter().tuple_windows() {
-                next.push(second - first);
+                next.push(*second - *first);
             }
             self.derived.push(next);
             current = self.derived.last().expect("Empty history");
         }
-        self.derived.push(vec![0; current.len() - 1]);
+        self.derived
+            .push(vec![T::default(); current.len().saturating_sub(1)]);
         self
     }
 
-    fn extrapolated(mut self, kind: ExtrapolationKind) -> History {
+    fn extrapolated(mut self, kind: ExtrapolationKind) -> Self {
         let mut rows = once(&mut self.data).chain(&mut self.derived).rev();
-        rows.next().expect("Empty history").push(0);
+        rows.next().expect("Empty history").push(T::default());
 
-        let mut seed = 0;
+        let mut seed = T::default();
         match kind {
             Forwards => rows.for_each(|row| {
                 let last = *row.last().expect("Empty history");
-                seed += last;
+                seed = seed + last;
                 row.push(seed);
             }),
             Backwards => rows.for_each(|row| {
@@ -80,17 +93,69 @@ impl History {
         self
     }
 
-    fn last(&self) -> i32 {
-        *self.data.last().expect("Empty history")
+    // Walks the derived table once in both directions, returning the previous and next values
+    fn extrapolate_both(self) -> (T, T) {
+        once(&self.data).chain(&self.derived).rev().fold(
+            (T::default(), T::default()),
+            |(prev, next), row| match (row.first(), row.last()) {
+                (Some(first), Some(last)) => (*first - prev, next + *last),
+                _ => (prev, next),
+            },
+        )
     }
 
-    fn first(&self) -> i32 {
-        *self.data.first().expect("Empty history")
+    // Extrapolates n new values, the farthest one ends up at the end (or start) of the data
+    #[allow(dead_code)]
+    fn extrapolate_n(self, kind: ExtrapolationKind, n: usize) -> Self {
+        (0..n).fold(self, |history, _| history.extrapolated(kind))
+    }
+
+    // The triangular difference table without the original data,
+    // the last row is always all zeros after derived()
+    #[allow(dead_code)]
+    fn table(&self) -> &[Vec<T>] {
+        &self.derived
     }
 }
 
-fn parse_history(input: &mut &str) -> PResult<History> {
-    let data = separated(1.., dec_int::<_, i32, _>, multispace1).parse_next(input)?;
+impl<T: Element + Into<i128>> History<T> {
+    // Coefficients of the polynomial going through the data points at x = 0, 1, 2, ...,
+    // starting from the constant term. Built with Newton's forward difference formula:
+    // p(x) = sum(Δᵏy₀ * C(x, k)), where Δᵏy₀ are the first values of the derived rows
+    #[allow(dead_code)]
+    fn fit_polynomial(&self) -> Vec<f64> {
+        let mut coefficients = vec![0.0; self.data.len()];
+        // Coefficients of C(x, k)
+        let mut basis = vec![1.0];
+        for (k, row) in once(&self.data).chain(&self.derived).enumerate() {
+            let difference = row.first().map_or(0.0, |value| (*value).into() as f64);
+            for (coefficient, basis_coefficient) in coefficients.iter_mut().zip(&basis) {
+                *coefficient += difference * basis_coefficient;
+            }
+
+            // C(x, k + 1) = C(x, k) * (x - k) / (k + 1)
+            let k = k as f64;
+            let mut next_basis = vec![0.0; basis.len() + 1];
+            for (power, basis_coefficient) in basis.iter().enumerate() {
+                next_basis[power + 1] += basis_coefficient / (k + 1.0);
+                next_basis[power] -= basis_coefficient * k / (k + 1.0);
+            }
+            basis = next_basis;
+        }
+        coefficients
+    }
+}
+
+#[allow(dead_code)]
+fn evaluate_polynomial(coefficients: &[f64], x: f64) -> f64 {
+    coefficients
+        .iter()
+        .rev()
+        .fold(0.0, |acc, coefficient| acc * x + coefficient)
+}
+
+fn parse_history(input: &mut &str) -> PResult<History<i64>> {
+    let data = separated(1.., dec_int::<_, i64, _>, multispace1).parse_next(input)?;
     Ok(History::new(data))
 }
 
@@ -102,30 +167,33 @@ impl AocTask for Day09 {
     fn solution(&self, input: AocStringIter, phase: usize) -> Result<AocSolution, BoxedError> {
         let report = input.map(|line| parse_history.parse(&line).map_err(|err| err.to_string()));
 
-        match phase {
-            1 => report
-                .map_ok(|hist| hist.extrapolated(Forwards))
-                .map_ok(|hist| hist.last())
-                .process_results(|iter| iter.sum::<i32>()),
-            2 => report
-                .map_ok(|hist| hist.extrapolated(Backwards))
-                .map_ok(|hist| hist.first())
-                .process_results(|iter| iter.sum::<i32>()),
+        let kind = match phase {
+            1 => Forwards,
+            2 => Backwards,
             _ => unimplemented!(),
-        }
-        .try_solved()
+        };
+
+        report
+            .map_ok(|hist| match (kind, hist.extrapolate_both()) {
+                (Forwards, (_, next)) => next,
+                (Backwards, (prev, _)) => prev,
+            })
+            .process_results(|iter| iter.sum::<i64>())
+            .try_solved()
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{ExtrapolationKind::*, History};
+    use winnow::Parser;
+
+    use super::{evaluate_polynomial, parse_history, ExtrapolationKind::*, History};
 
     #[test]
     fn single_number_history() {
         let history = History::new(vec![5]);
         assert_eq!(history.data, vec![5]);
-        assert_eq!(history.derived, vec![vec![]]);
+        assert_eq!(history.derived, vec![Vec::<i64>::new()]);
 
         let forwards_extrapolated = history.clone().extrapolated(Forwards);
         assert_eq!(forwards_extrapolated.data, vec![5, 5]);
@@ -135,4 +203,89 @@ mod test {
         assert_eq!(backwards_extrapolated.data, vec![5, 5]);
         assert_eq!(backwards_extrapolated.derived, vec![vec![0]]);
     }
+
+    #[test]
+    fn extrapolate_n_steps() {
+        let history = History::new(vec![0, 3, 6, 9]);
+
+        let forwards_extrapolated = history.clone().extrapolate_n(Forwards, 3);
+        assert_eq!(forwards_extrapolated.data, vec![0, 3, 6, 9, 12, 15, 18]);
+        assert_eq!(forwards_extrapolated.data.last(), Some(&18));
+
+        let backwards_extrapolated = history.extrapolate_n(Backwards, 2);
+        assert_eq!(backwards_extrapolated.data, vec![-6, -3, 0, 3, 6, 9]);
+        assert_eq!(backwards_extrapolated.data.first(), Some(&-6));
+    }
+
+    #[test]
+    fn values_near_i32_max() {
+        let history = parse_history
+            .parse("2147483645 2147483646 2147483647")
+            .unwrap();
+        assert_eq!(
+            history.extrapolated(Forwards).data.last(),
+            Some(&2147483648)
+        );
+
+        let history = History::new(vec![-2147483647i64, -2147483648]);
+        assert_eq!(
+            history.extrapolated(Forwards).data.last(),
+            Some(&-2147483649)
+        );
+    }
+
+    #[test]
+    fn polynomial_fit_matches_extrapolation() {
+        for data in [
+            vec![0, 3, 6, 9, 12, 15],
+            vec![1, 3, 6, 10, 15, 21],
+            vec![10, 13, 16, 21, 30, 45],
+        ] {
+            let history = History::new(data);
+            let coefficients = history.fit_polynomial();
+            let x = history.data.len() as f64;
+            let next = *history.extrapolated(Forwards).data.last().unwrap();
+            assert_eq!(evaluate_polynomial(&coefficients, x).round() as i32, next);
+        }
+    }
+
+    #[test]
+    fn extrapolate_both_sample() {
+        let samples = [
+            (vec![0, 3, 6, 9, 12, 15], (-3, 18)),
+            (vec![1, 3, 6, 10, 15, 21], (0, 28)),
+            (vec![10, 13, 16, 21, 30, 45], (5, 68)),
+        ];
+        for (data, expected) in samples {
+            let history = History::new(data);
+            assert_eq!(history.clone().extrapolate_both(), expected);
+            assert_eq!(
+                (
+                    history.clone().extrapolated(Backwards).data[0],
+                    *history.extrapolated(Forwards).data.last().unwrap()
+                ),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn two_number_history() {
+        let history = History::new(vec![1, 4]);
+        assert_eq!(history.derived, vec![vec![3], vec![]]);
+        assert_eq!(history.clone().extrapolated(Forwards).data, vec![1, 4, 7]);
+        assert_eq!(history.clone().extrapolated(Backwards).data, vec![-2, 1, 4]);
+        assert_eq!(history.extrapolate_both(), (-2, 7));
+    }
+
+    #[test]
+    fn difference_table() {
+        let history = History::new(vec![0, 3, 6, 9]);
+        assert_eq!(history.table().len(), 2);
+        assert_eq!(history.table(), &[vec![3, 3, 3], vec![0, 0]]);
+        assert_eq!(
+            history.to_string(),
+            "[0, 3, 6, 9]\n  [3, 3, 3]\n    [0, 0]\n"
+        );
+    }
 }
@@ -1,6 +1,7 @@
 use std::{
     collections::{hash_map::Entry, HashMap, VecDeque},
     fmt::Display,
+    iter::once,
     ops::{Add, Sub},
     path::PathBuf,
 };
@@ -54,25 +55,30 @@ impl From<char> for Tile {
     }
 }
 
+const WEST: Pos = Pos::new(-1, 0);
+const NORTH: Pos = Pos::new(0, -1);
+const SOUTH: Pos = Pos::new(0, 1);
+const EAST: Pos = Pos::new(1, 0);
+
 impl Tile {
-    fn connections(&self) -> Vec<Pos> {
+    // Connections are sorted, static slices avoid allocating on every neighbour check
+    fn connections(&self) -> &'static [Pos] {
         match self {
-            Tile::NorthWest => vec![Pos::new(-1, 0), Pos::new(0, -1)],
-            Tile::SouthWest => vec![Pos::new(-1, 0), Pos::new(0, 1)],
-            Tile::Horizontal => vec![Pos::new(-1, 0), Pos::new(1, 0)],
-            Tile::Vertical => vec![Pos::new(0, -1), Pos::new(0, 1)],
-            Tile::NorthEast => vec![Pos::new(0, -1), Pos::new(1, 0)],
-            Tile::SouthEast => vec![Pos::new(0, 1), Pos::new(1, 0)],
-            Tile::Start => vec![
-                Pos::new(-1, 0),
-                Pos::new(0, -1),
-                Pos::new(0, 1),
-                Pos::new(1, 0),
-            ],
-            _ => vec![],
+            Tile::NorthWest => &[WEST, NORTH],
+            Tile::SouthWest => &[WEST, SOUTH],
+            Tile::Horizontal => &[WEST, EAST],
+            Tile::Vertical => &[NORTH, SOUTH],
+            Tile::NorthEast => &[NORTH, EAST],
+            Tile::SouthEast => &[SOUTH, EAST],
+            Tile::Start => &[WEST, NORTH, SOUTH, EAST],
+            _ => &[],
         }
     }
 
+    fn connects_to(&self, dir: Pos) -> bool {
+        self.connections().contains(&dir)
+    }
+
     fn from_connections(mut connections: Vec<Pos>) -> Self {
         connections.sort();
         if connections == Tile::Vertical.connections() {
@@ -92,21 +98,12 @@ impl Tile {
         }
     }
 
-    fn is_vertical_edge(&self) -> bool {
-        matches!(
-            self,
-            Tile::Vertical | Tile::NorthEast | Tile::NorthWest | Tile::SouthWest | Tile::SouthEast
-        )
-    }
-
-    fn forms_left_diagonal_with(&self, other: &Tile) -> bool {
-        match (self, other) {
-            //┏┛ (reverse)
-            (Tile::NorthWest, Tile::SouthEast) => true,
-            //┗┓ (reverse)
-            (Tile::SouthWest, Tile::NorthEast) => true,
-            _ => false,
-        }
+    // A corner leaving the ray towards `dir`, followed by one bending to the other side of the
+    // ray, form a diagonal which is crossed only once, e.g. ┏━┛ going west
+    fn forms_diagonal_with(&self, other: &Tile, dir: Pos, side: Pos) -> bool {
+        self.connects_to(dir)
+            && other.connects_to(Pos::new(0, 0) - dir)
+            && self.connects_to(side) != other.connects_to(side)
     }
 }
 
@@ -125,7 +122,7 @@ struct Pos {
 }
 
 impl Pos {
-    fn new(x: i32, y: i32) -> Self {
+    const fn new(x: i32, y: i32) -> Self {
         Self { x, y }
     }
 }
@@ -153,11 +150,15 @@ impl Sub for Pos {
 }
 
 impl Field {
-    fn new(input: AocStringIter) -> Self {
+    fn new(mut input: AocStringIter) -> Self {
+        Self::from_str(&input.join("\n"))
+    }
+
+    fn from_str(input: &str) -> Self {
         let mut tiles: Vec<Vec<Tile>> = vec![];
         let mut start = None;
 
-        for (y, row) in input.enumerate() {
+        for (y, row) in input.lines().enumerate() {
             for (x, chr) in row.chars().enumerate() {
                 let tile: Tile = chr.into();
                 if matches!(tile, Tile::Start) && start.is_none() {
@@ -179,6 +180,8 @@ impl Field {
         }
     }
 
+    // Breadth-first search, the queue always holds tiles in the order of their distance,
+    // so the first distance assigned to a tile is the shortest one, even if the loop branches
     fn compute_distances(&mut self) {
         // (tile, distance from start)
         let mut tiles_to_visit = VecDeque::from([(self.start, 0)]);
@@ -194,52 +197,117 @@ impl Field {
         }
     }
 
-    fn guess_start_tile(&mut self) {
+    // Position and distance of the loop tile farthest from the start
+    fn farthest(&self) -> Option<(Pos, usize)> {
+        self.distances
+            .iter()
+            .max_by_key(|(_, distance)| **distance)
+            .map(|(pos, distance)| (*pos, *distance))
+    }
+
+    fn guess_start_tile(&mut self) -> Result<(), BoxedError> {
         let start = self.start;
         let first_tile_connections = self
             .distances
             .iter()
             .filter_map(|(pos, dist)| (*dist == 1).then_some(*pos - start))
             .collect_vec();
+        if first_tile_connections.len() != 2 {
+            let candidates = first_tile_connections
+                .iter()
+                .map(|connection| start + *connection)
+                .sorted()
+                .collect_vec();
+            return Err(format!(
+                "Ambiguous start tile, expected 2 connected neighbors, found: {candidates:?}"
+            )
+            .into());
+        }
+
         let start_tile = Tile::from_connections(first_tile_connections);
         self.tiles[start.y as usize][start.x as usize] = start_tile;
+        Ok(())
     }
 
-    fn compute_area(&mut self) -> usize {
-        self.guess_start_tile();
+    fn compute_area(&mut self) -> Result<usize, BoxedError> {
+        self.guess_start_tile()?;
         let mut area = 0;
         for y in 0..self.height {
             for x in 0..self.width {
                 let pos = Pos::new(x, y);
-                if self.distances.contains_key(&pos) {
-                    continue;
-                }
-                // If a tile is inside the loop, it will be surrounded by an odd number of edges on
-                // any side
-                if self.edges_to_the_left(&pos) % 2 == 1 {
+                // Rays cast in perpendicular directions have to agree on the parity
+                debug_assert!(
+                    self.distances.contains_key(&pos)
+                        || self.edges_along(&pos, WEST) % 2 == self.edges_along(&pos, NORTH) % 2
+                );
+                if self.is_interior(&pos) {
                     area += 1;
                 }
             }
         }
-        area
+        Ok(area)
+    }
+
+    fn is_interior(&self, pos: &Pos) -> bool {
+        // If a tile is inside the loop, it will be surrounded by an odd number of edges on
+        // any side
+        !self.distances.contains_key(pos) && self.edges_along(pos, WEST) % 2 == 1
+    }
+
+    // Walks the loop from the start, returning the positions in order
+    fn loop_path(&self) -> Vec<Pos> {
+        let mut path = vec![self.start];
+        let mut previous = self.start;
+        let mut current = self.start;
+        while let Some(next) = self
+            .connections_at(&current)
+            .into_iter()
+            .find(|next| *next != previous)
+        {
+            if next == self.start {
+                break;
+            }
+            path.push(next);
+            previous = current;
+            current = next;
+        }
+        path
     }
 
-    fn edges_to_the_left(&self, pos: &Pos) -> usize {
-        let dir = Pos::new(-1, 0);
+    // The loop area from the shoelace formula, with the interior tile count from Pick's theorem:
+    // area = interior + boundary / 2 - 1
+    #[allow(dead_code)]
+    fn area_shoelace(&self) -> usize {
+        let path = self.loop_path();
+        let double_area = path
+            .iter()
+            .chain(path.first())
+            .tuple_windows()
+            .map(|(a, b)| a.x as i64 * b.y as i64 - b.x as i64 * a.y as i64)
+            .sum::<i64>()
+            .unsigned_abs() as usize;
+        (double_area + 2 - path.len()) / 2
+    }
+
+    // Counts the loop walls crossed by a ray cast from pos in the given direction
+    fn edges_along(&self, pos: &Pos, dir: Pos) -> usize {
+        let side = Pos::new(dir.y, dir.x);
+        let crosses =
+            |tile: &Tile| tile.connects_to(side) || tile.connects_to(Pos::new(0, 0) - side);
         let mut edges = 0;
         let mut new = *pos + dir;
         let mut last_tile = &Tile::Ground;
-        while new.x >= 0 {
-            let tile = self.tile_at(&new);
+        while new.x >= 0 && new.y >= 0 && new.x < self.width && new.y < self.height {
+            let tile = match self.distances.contains_key(&new) {
+                true => self.tile_at(&new),
+                false => &Tile::Ground,
+            };
             // If two corners form a diagonoal, ignore the 2nd corner and count them as 1 wall
-            if !last_tile.forms_left_diagonal_with(tile)
-                && tile.is_vertical_edge()
-                && self.distances.contains_key(&new)
-            {
+            if crosses(tile) && !last_tile.forms_diagonal_with(tile, dir, side) {
                 edges += 1;
             }
-            // Ignore horizontal pipes to check if the whole segment is a diagonal above
-            if !matches!(tile, Tile::Horizontal) {
+            // Ignore pipes parallel to the ray to check if the whole segment is a diagonal
+            if crosses(tile) || !tile.connects_to(dir) {
                 last_tile = tile;
             }
             new = new + dir;
@@ -254,13 +322,57 @@ impl Field {
     fn connections_at(&self, pos: &Pos) -> Vec<Pos> {
         self.tile_at(pos)
             .connections()
-            .into_iter()
-            .map(|connection| *pos + connection)
+            .iter()
+            .map(|connection| *pos + *connection)
             .filter(|new| new.x >= 0 && new.y >= 0 && new.x < self.width && new.y < self.height)
-            .filter(|new| self.tile_at(new).connections().contains(&(*pos - *new)))
+            .filter(|new| self.tile_at(new).connects_to(*pos - *new))
             .collect()
     }
 
+    // Renders the loop as line segments and the interior as filled squares,
+    // the start tile should be guessed beforehand
+    #[allow(dead_code)]
+    fn to_svg(&self) -> String {
+        const CELL: i32 = 10;
+        let header = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}">"#,
+            self.width * CELL,
+            self.height * CELL
+        );
+
+        let interior = (0..self.height)
+            .cartesian_product(0..self.width)
+            .map(|(y, x)| Pos::new(x, y))
+            .filter(|pos| self.is_interior(pos))
+            .map(|pos| {
+                format!(
+                    r#"  <rect x="{}" y="{}" width="{CELL}" height="{CELL}" fill="green" />"#,
+                    pos.x * CELL,
+                    pos.y * CELL
+                )
+            });
+
+        // Each loop tile is drawn as segments from its center towards its connections
+        let segments = self.distances.keys().sorted().flat_map(|pos| {
+            let center = Pos::new(pos.x * CELL + CELL / 2, pos.y * CELL + CELL / 2);
+            self.tile_at(pos).connections().iter().map(move |dir| {
+                format!(
+                    r#"  <line x1="{}" y1="{}" x2="{}" y2="{}" stroke="black" />"#,
+                    center.x,
+                    center.y,
+                    center.x + dir.x * CELL / 2,
+                    center.y + dir.y * CELL / 2
+                )
+            })
+        });
+
+        once(header)
+            .chain(interior)
+            .chain(segments)
+            .chain(once("</svg>".to_string()))
+            .join("\n")
+    }
+
     #[allow(dead_code)]
     fn debug(&self, color: bool) {
         for (y, row) in self.tiles.iter().enumerate() {
@@ -289,11 +401,11 @@ impl AocTask for Day10 {
         let mut field = Field::new(input);
         // field.debug(true);
         field.compute_distances();
-        field.guess_start_tile();
+        field.guess_start_tile()?;
         // field.debug(true);
 
         match phase {
-            1 => field.distances.values().max().solved(),
+            1 => field.farthest().map(|(_, distance)| distance).solved(),
             // Since the Start tile in the input data doesn't touch any tiles that are not part of the loop
             // and have an open connection to it, we can assume that all keys in field.distances are part of the loop.
             // This:
@@ -302,8 +414,161 @@ impl AocTask for Day10 {
             //  ┛ ┃  ┃
             //    ┗━━┛
             // does not happen.
-            2 => field.compute_area().solved(),
+            2 => field.compute_area()?.solved(),
             _ => unimplemented!(),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    extern crate test;
+
+    use test::Bencher;
+
+    use itertools::Itertools;
+
+    use super::{Field, Pos, EAST, NORTH, SOUTH, WEST};
+
+    const LOOP_SAMPLE: &str = "...........
+.S-------7.
+.|F-----7|.
+.||.....||.
+.||.....||.
+.|L-7.F-J|.
+.|..|.|..|.
+.L--J.L--J.
+...........";
+
+    const LARGER_SAMPLE: &str = ".F----7F7F7F7F-7....
+.|F--7||||||||FJ....
+.||.FJ||||||||L7....
+FJL7L7LJLJ||LJ.L-7..
+L--J.L7...LJS7F-7L7.
+....F-J..F7FJ|L7L7L7
+....L7.F7||L7|.L7L7|
+.....|FJLJ|FJ|F7|.LJ
+....FJL-7.||.||||...
+....L---J.LJ.LJLJ...";
+
+    fn field(input: &str) -> Field {
+        let mut field = Field::from_str(input);
+        field.compute_distances();
+        field
+    }
+
+    #[test]
+    fn field_from_str() {
+        let field = Field::from_str(LOOP_SAMPLE);
+        assert_eq!(field.start, Pos::new(1, 1));
+        assert_eq!((field.width, field.height), (11, 9));
+
+        let larger = Field::from_str(LARGER_SAMPLE);
+        assert_eq!(larger.start, Pos::new(12, 4));
+    }
+
+    #[test]
+    fn shoelace_matches_ray_casting() {
+        for (input, expected) in [(LOOP_SAMPLE, 4), (LARGER_SAMPLE, 8)] {
+            let mut field = field(input);
+            assert_eq!(field.area_shoelace(), expected);
+            assert_eq!(field.compute_area().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn svg_interior_cells() {
+        let mut field = field(LOOP_SAMPLE);
+        field.guess_start_tile().unwrap();
+        let svg = field.to_svg();
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<rect").count(), 4);
+        assert_eq!(svg.matches("<line").count(), field.distances.len() * 2);
+    }
+
+    #[test]
+    fn ambiguous_start_tile() {
+        let mut field = field(
+            ".....
+.F-7.
+-S.|.
+.L-J.
+.....",
+        );
+        let error = field.guess_start_tile().unwrap_err();
+        assert!(error.to_string().contains("found: [Pos { x: 0, y: 2 }"));
+    }
+
+    #[test]
+    fn farthest_tile() {
+        let field = field(
+            "..F7.
+.FJ|.
+SJ.L7
+|F--J
+LJ...",
+        );
+        assert_eq!(field.farthest(), Some((Pos::new(4, 2), 8)));
+    }
+
+    #[test]
+    fn figure_eight_distances() {
+        let field = field(
+            "F--7...
+|..|...
+L--S--7
+...|..|
+...L--J",
+        );
+        assert_eq!(field.distances.len(), 19);
+        assert_eq!(field.distances[&Pos::new(3, 0)], 2);
+        assert_eq!(field.distances[&Pos::new(1, 2)], 2);
+        assert_eq!(field.distances[&Pos::new(0, 0)], 5);
+        assert_eq!(field.distances[&Pos::new(6, 4)], 5);
+        assert_eq!(field.farthest().map(|(_, distance)| distance), Some(5));
+    }
+
+    // A single loop snaking through every tile of a size x size field, size has to be even
+    fn serpentine(size: usize) -> String {
+        let mut rows = vec![format!("S{}7", "-".repeat(size - 2))];
+        for y in 1..size {
+            let left = if y == size - 1 { 'L' } else { '|' };
+            let row = if y == size - 1 {
+                format!("{}J", "-".repeat(size - 2))
+            } else if y % 2 == 1 {
+                format!("F{}J", "-".repeat(size - 3))
+            } else {
+                format!("L{}7", "-".repeat(size - 3))
+            };
+            rows.push(format!("{left}{row}"));
+        }
+        rows.join("\n")
+    }
+
+    #[test]
+    fn serpentine_loop() {
+        let field = field(&serpentine(6));
+        assert_eq!(field.distances.len(), 36);
+        assert_eq!(field.farthest().map(|(_, distance)| distance), Some(18));
+    }
+
+    #[bench]
+    fn distances_500x500(b: &mut Bencher) {
+        let input = serpentine(500);
+        b.iter(|| field(&input).distances.len());
+    }
+
+    #[test]
+    fn rays_in_all_directions_agree() {
+        let mut field = field(LARGER_SAMPLE);
+        field.guess_start_tile().unwrap();
+        for (y, x) in (0..field.height).cartesian_product(0..field.width) {
+            let pos = Pos::new(x, y);
+            if field.distances.contains_key(&pos) {
+                continue;
+            }
+            let parities = [WEST, NORTH, EAST, SOUTH].map(|dir| field.edges_along(&pos, dir) % 2);
+            assert!(parities.iter().all_equal(), "{pos:?}: {parities:?}");
+        }
+    }
+}
@@ -3,8 +3,41 @@ use std::{collections::HashMap, path::PathBuf};
 use crate::BoxedError;
 use aoc_framework::{traits::UnitSolved, AocSolution, AocStringIter, AocTask};
 use itertools::Itertools;
+use rayon::prelude::*;
 
-pub struct Day11;
+#[derive(Default)]
+pub struct Day11 {
+    metric: Metric,
+}
+
+impl Day11 {
+    #[allow(dead_code)]
+    pub fn with_metric(metric: Metric) -> Self {
+        Self { metric }
+    }
+}
+
+// Manhattan distance is the one the puzzle asks for, Euclidean distances are rounded
+#[derive(Debug, Default, Clone, Copy)]
+pub enum Metric {
+    #[default]
+    Manhattan,
+    #[allow(dead_code)]
+    Chebyshev,
+    #[allow(dead_code)]
+    Euclidean,
+}
+
+impl Metric {
+    fn distance(&self, a: &[usize; 2], b: &[usize; 2]) -> usize {
+        let (dx, dy) = (a[0].abs_diff(b[0]), a[1].abs_diff(b[1]));
+        match self {
+            Metric::Manhattan => dx + dy,
+            Metric::Chebyshev => dx.max(dy),
+            Metric::Euclidean => ((dx * dx + dy * dy) as f64).sqrt().round() as usize,
+        }
+    }
+}
 
 struct Universe {
     galaxies: Vec<[usize; 2]>,
@@ -34,7 +67,9 @@ impl Universe {
         }
     }
 
-    fn compute_real_coordinates(&mut self, distnace_factor: usize) {
+    // Expanded galaxy coordinates, every empty row/col is replaced by distance_factor rows/cols
+    fn compute_real_coordinates(&self, distance_factor: usize) -> Vec<[usize; 2]> {
+        let mut real = self.galaxies.clone();
         for (axis, coordinate_idx) in [(&self.cols, 0), (&self.rows, 1)] {
             let size = *axis.keys().max().expect("At least one galaxy should exist");
 
@@ -43,18 +78,90 @@ impl Universe {
             for coordinate in 0..=size {
                 if axis.contains_key(&coordinate) {
                     if empty_space != 0 {
-                        offset += empty_space * (distnace_factor - 1);
+                        offset += empty_space * (distance_factor - 1);
                         empty_space = 0;
                     }
                     axis.get(&coordinate)
                         .expect("Galaxy should exist since the axis contains its key")
                         .iter()
-                        .for_each(|idx| self.galaxies[*idx][coordinate_idx] += offset);
+                        .for_each(|idx| real[*idx][coordinate_idx] += offset);
                 } else {
                     empty_space += 1;
                 }
             }
+            // The axis ends on a populated coordinate, trailing empty space is never counted
+            debug_assert_eq!(empty_space, 0);
         }
+        real
+    }
+
+    // Empty rows/cols between the galaxies, the ones which get expanded
+    #[allow(dead_code)]
+    fn empty_rows(&self) -> Vec<usize> {
+        Self::gaps(&self.rows)
+    }
+
+    #[allow(dead_code)]
+    fn empty_cols(&self) -> Vec<usize> {
+        Self::gaps(&self.cols)
+    }
+
+    fn gaps(axis: &HashMap<usize, Vec<usize>>) -> Vec<usize> {
+        let Some(size) = axis.keys().max() else {
+            return vec![];
+        };
+        (0..=*size)
+            .filter(|coordinate| !axis.contains_key(coordinate))
+            .collect()
+    }
+
+    // Sum of distances between each combination of 2 galaxies, using the puzzle's metric
+    fn total_distance(&self, factor: usize) -> usize {
+        self.total_distance_with(factor, Metric::default())
+    }
+
+    fn total_distance_with(&self, factor: usize, metric: Metric) -> usize {
+        self.compute_real_coordinates(factor)
+            .iter()
+            .tuple_combinations()
+            .map(|(a, b)| metric.distance(a, b))
+            .sum()
+    }
+
+    // Manhattan distance is separable, so each axis can be summed on its own. With sorted
+    // coordinates, the i-th one is the farther one in exactly i pairs, contributing
+    // coord[i] * i - (coord[0] + ... + coord[i - 1]) to the total
+    #[allow(dead_code)]
+    fn total_distance_fast(&self, factor: usize) -> usize {
+        let real = self.compute_real_coordinates(factor);
+        (0..2)
+            .map(|axis| {
+                let coordinates = real.iter().map(|galaxy| galaxy[axis]).sorted();
+                let mut prefix_sum = 0;
+                let mut total = 0;
+                for (idx, coordinate) in coordinates.enumerate() {
+                    total += coordinate * idx - prefix_sum;
+                    prefix_sum += coordinate;
+                }
+                total
+            })
+            .sum()
+    }
+
+    // Each galaxy sums the distances to the galaxies after it, the partial sums are reduced
+    #[allow(dead_code)]
+    fn total_distance_par(&self, factor: usize) -> usize {
+        let metric = Metric::default();
+        let real = self.compute_real_coordinates(factor);
+        real.par_iter()
+            .enumerate()
+            .map(|(idx, a)| {
+                real[idx + 1..]
+                    .iter()
+                    .map(|b| metric.distance(a, b))
+                    .sum::<usize>()
+            })
+            .sum()
     }
 }
 
@@ -64,20 +171,151 @@ impl AocTask for Day11 {
     }
 
     fn solution(&self, input: AocStringIter, phase: usize) -> Result<AocSolution, BoxedError> {
-        let mut universe = Universe::new(input);
-        match phase {
-            1 => universe.compute_real_coordinates(2),
-            2 => universe.compute_real_coordinates(10usize.pow(6)),
+        let universe = Universe::new(input);
+        let factor = match phase {
+            1 => 2,
+            2 => 10usize.pow(6),
             _ => unimplemented!(),
+        };
+
+        match self.metric {
+            Metric::Manhattan => universe.total_distance(factor),
+            metric => universe.total_distance_with(factor, metric),
         }
+        .solved()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use itertools::Itertools;
+
+    use super::{Metric, Universe};
+
+    const SAMPLE: &str = "...#......
+.......#..
+#.........
+..........
+......#...
+.#........
+.........#
+..........
+.......#..
+#...#.....";
+
+    fn universe(input: &str) -> Universe {
+        Universe::new(Box::new(input.lines().map(String::from)))
+    }
+
+    #[test]
+    fn expansion_factors() {
+        let universe = universe(SAMPLE);
+        assert_eq!(universe.total_distance(2), 374);
+        assert_eq!(universe.total_distance(10), 1030);
+        assert_eq!(universe.total_distance(100), 8410);
+    }
+
+    #[test]
+    fn chebyshev_metric() {
+        // The empty row doubles, moving the galaxies to (0, 0), (2, 3) and (1, 4)
+        let universe = universe(
+            "#..
+...
+..#
+.#.",
+        );
+        assert_eq!(
+            universe.compute_real_coordinates(2),
+            vec![[0, 0], [2, 3], [1, 4]]
+        );
+        assert_eq!(
+            universe.total_distance_with(2, Metric::Manhattan),
+            5 + 5 + 2
+        );
+        assert_eq!(
+            universe.total_distance_with(2, Metric::Chebyshev),
+            3 + 4 + 1
+        );
+    }
+
+    #[test]
+    fn galaxies_on_the_boundary() {
+        let corners = universe(
+            "#...
+....
+...#",
+        );
+        assert_eq!(corners.compute_real_coordinates(3), vec![[0, 0], [7, 4]]);
+
+        // Leading empty space shifts the galaxies, trailing empty space has no effect
+        let padded = universe(
+            "......
+..#...
+......
+...#..
+......",
+        );
+        assert_eq!(padded.compute_real_coordinates(3), vec![[6, 3], [7, 7]]);
+    }
 
-        // Sum distances between each combination of 2 galaxies
-        universe
-            .galaxies
+    #[test]
+    fn parallel_sum_matches_sequential() {
+        let universe = universe(SAMPLE);
+        for factor in [2, 10, 100] {
+            assert_eq!(
+                universe.total_distance_par(factor),
+                universe.total_distance(factor)
+            );
+        }
+    }
+
+    #[test]
+    fn fast_sum_matches_brute_force() {
+        let universe = universe(SAMPLE);
+        for factor in [2, 10, 100, 10usize.pow(6)] {
+            assert_eq!(
+                universe.total_distance_fast(factor),
+                universe.total_distance(factor)
+            );
+        }
+    }
+
+    #[test]
+    fn fast_sum_random_galaxies() {
+        const SIZE: usize = 300;
+        let mut grid = vec![vec!['.'; SIZE]; SIZE];
+        // Simple LCG, good enough to scatter the galaxies
+        let mut state: u64 = 0x2023_1211;
+        let mut placed = 0;
+        while placed < 1000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let (x, y) = ((state >> 33) as usize % SIZE, (state >> 17) as usize % SIZE);
+            if grid[y][x] == '.' {
+                grid[y][x] = '#';
+                placed += 1;
+            }
+        }
+        let input = grid
             .iter()
-            .tuple_combinations()
-            .map(|(a, b)| a[0].abs_diff(b[0]) + a[1].abs_diff(b[1]))
-            .sum::<usize>()
-            .solved()
+            .map(|row| row.iter().collect::<String>())
+            .join("\n");
+
+        let universe = universe(&input);
+        assert_eq!(universe.galaxies.len(), 1000);
+        for factor in [2, 10usize.pow(6)] {
+            assert_eq!(
+                universe.total_distance_fast(factor),
+                universe.total_distance(factor)
+            );
+        }
+    }
+
+    #[test]
+    fn empty_rows_and_cols() {
+        let universe = universe(SAMPLE);
+        assert_eq!(universe.empty_rows(), vec![3, 7]);
+        assert_eq!(universe.empty_cols(), vec![2, 5, 8]);
     }
 }
@@ -1,8 +1,9 @@
 use std::{collections::HashMap, iter::once, path::PathBuf};
 
-use crate::BoxedError;
+use crate::{context::Context, BoxedError};
 use aoc_framework::{traits::*, AocSolution, AocStringIter, AocTask};
 use itertools::{repeat_n, Itertools};
+use rayon::prelude::*;
 use winnow::{
     ascii::{digit1, space1},
     combinator::{empty, fail, repeat, separated, seq},
@@ -11,7 +12,79 @@ use winnow::{
     PResult, Parser,
 };
 
-pub struct Day12;
+pub struct Day12 {
+    // How many copies of each record are joined together in phase 2
+    unfold: usize,
+}
+
+impl Default for Day12 {
+    fn default() -> Self {
+        Self { unfold: 5 }
+    }
+}
+
+impl Day12 {
+    #[allow(dead_code)]
+    pub fn with_unfold(unfold: usize) -> Result<Self, BoxedError> {
+        // Unfolding 0 times would leave records without any springs or groups
+        if unfold == 0 {
+            return Err("Records should be unfolded at least once".into());
+        }
+        Ok(Self { unfold })
+    }
+
+    fn parse_records(input: AocStringIter) -> Result<Vec<SpringRecord>, BoxedError> {
+        input
+            .map(|input| {
+                parse_spring_record
+                    .parse(&input)
+                    .context("parsing spring record")
+            })
+            .collect()
+    }
+
+    // Solves both phases with a single pass over the input
+    #[allow(dead_code)]
+    pub fn both(&self, input: AocStringIter) -> Result<(usize, usize), BoxedError> {
+        let records = Self::parse_records(input)?;
+        Ok((
+            self.total_arrangements(records.clone(), 1),
+            self.total_arrangements(records, 2),
+        ))
+    }
+
+    fn sum_arrangements(records: &[SpringRecord]) -> usize {
+        records
+            .iter()
+            .map(|record| record.compute_arrangements_dfa())
+            .sum()
+    }
+
+    fn total_arrangements(&self, records: Vec<SpringRecord>, phase: usize) -> usize {
+        let records = match phase {
+            1 => records,
+            2 => records
+                .into_iter()
+                .map(|record| record.multiply_by(self.unfold))
+                .collect(),
+            _ => unimplemented!(),
+        };
+
+        if cfg!(feature = "parallel-springs") {
+            Self::sum_arrangements_par(&records)
+        } else {
+            Self::sum_arrangements(&records)
+        }
+    }
+
+    // Records are independent, so they can be split between threads and summed afterwards
+    fn sum_arrangements_par(records: &[SpringRecord]) -> usize {
+        records
+            .par_iter()
+            .map(|record| record.compute_arrangements_dfa())
+            .sum()
+    }
+}
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 enum SpringState {
@@ -143,7 +216,89 @@ impl SpringRecord {
             .expect("The root state should be computed")
     }
 
+    #[allow(dead_code)]
+    fn compute_arrangements_memo(&self) -> usize {
+        self.arrangements_from(DPState::default(), &mut HashMap::new())
+    }
+
+    // Top-down version of compute_arrangements_dp, every state is computed once
+    fn arrangements_from(&self, state: DPState, memo: &mut HashMap<DPState, usize>) -> usize {
+        if let Some(result) = memo.get(&state) {
+            return *result;
+        }
+
+        let DPState {
+            spring_idx,
+            group_idx,
+            group_size,
+        } = state;
+        let current_group = self.damaged_groups.get(group_idx);
+
+        let result = if spring_idx == self.springs.len() {
+            let no_extra_damaged_springs =
+                group_idx == self.damaged_groups.len() && group_size == 0;
+            let last_group_valid =
+                group_idx + 1 == self.damaged_groups.len() && current_group == Some(&group_size);
+            (no_extra_damaged_springs || last_group_valid) as usize
+        } else {
+            let current_spring = &self.springs[spring_idx];
+            [SpringState::Damaged, SpringState::Operational]
+                .into_iter()
+                .filter(|possible_spring_state| {
+                    possible_spring_state == current_spring
+                        || SpringState::Unknown == *current_spring
+                })
+                .map(
+                    |possible_spring_state| match (possible_spring_state, group_size) {
+                        // No changes
+                        (SpringState::Operational, 0) => self.arrangements_from(state.ok(), memo),
+                        // Valid end of damaged group
+                        (SpringState::Operational, _) if current_group == Some(&group_size) => {
+                            self.arrangements_from(state.damaged_group_end(), memo)
+                        }
+                        // Continuation/Start of damaged group, unless it's already too long
+                        (SpringState::Damaged, _)
+                            if current_group.is_some_and(|length| group_size < *length) =>
+                        {
+                            self.arrangements_from(state.damaged(), memo)
+                        }
+                        // Invalid arrangements
+                        _ => 0,
+                    },
+                )
+                .sum()
+        };
+
+        memo.insert(state, result);
+        result
+    }
+
+    // The groups and the operational springs between them have to fit into the record
+    fn groups_fit(&self) -> bool {
+        let separators = self.damaged_groups.len().saturating_sub(1);
+        let required = self.damaged_groups.iter().sum::<usize>() + separators;
+        required <= self.springs.len()
+    }
+
+    // Some(0) if the springs can't possibly make up the damaged groups, None if the record
+    // has to be solved
+    fn trivial_bound(&self) -> Option<usize> {
+        let damaged = self.damaged_groups.iter().sum::<usize>();
+        let count = |state| {
+            self.springs
+                .iter()
+                .filter(|spring| **spring == state)
+                .count()
+        };
+        let known_damaged = count(SpringState::Damaged);
+        let maybe_damaged = known_damaged + count(SpringState::Unknown);
+        (known_damaged > damaged || maybe_damaged < damaged || !self.groups_fit()).then_some(0)
+    }
+
     fn compute_arrangements_dfa(&self) -> usize {
+        if let Some(bound) = self.trivial_bound() {
+            return bound;
+        }
         // Defines possible states
         let states = Itertools::intersperse(
             self.damaged_groups.iter().map(|length| {
@@ -207,6 +362,57 @@ impl SpringRecord {
         *current.last().expect("SpringRecord shouldn't be empty")
     }
 
+    // Every valid way of filling in the unknown springs, found by backtracking over them.
+    // The number of candidates doubles with each unknown spring, so large records are rejected
+    #[allow(dead_code)]
+    fn arrangements(&self) -> Result<Vec<Vec<SpringState>>, BoxedError> {
+        const MAX_UNKNOWN: usize = 20;
+        let unknown = self
+            .springs
+            .iter()
+            .positions(|spring| *spring == SpringState::Unknown)
+            .collect_vec();
+        if unknown.len() > MAX_UNKNOWN {
+            return Err(format!(
+                "Too many unknown springs to enumerate: {} > {MAX_UNKNOWN}",
+                unknown.len()
+            )
+            .into());
+        }
+
+        let mut arrangements = vec![];
+        self.fill_unknown(&mut self.springs.clone(), &unknown, &mut arrangements);
+        Ok(arrangements)
+    }
+
+    fn fill_unknown(
+        &self,
+        springs: &mut [SpringState],
+        unknown: &[usize],
+        arrangements: &mut Vec<Vec<SpringState>>,
+    ) {
+        let Some((idx, rest)) = unknown.split_first() else {
+            if self.matches_groups(springs) {
+                arrangements.push(springs.to_vec());
+            }
+            return;
+        };
+        for state in [SpringState::Operational, SpringState::Damaged] {
+            springs[*idx] = state;
+            self.fill_unknown(springs, rest, arrangements);
+        }
+        springs[*idx] = SpringState::Unknown;
+    }
+
+    fn matches_groups(&self, springs: &[SpringState]) -> bool {
+        springs
+            .iter()
+            .dedup_with_count()
+            .filter(|(_, spring)| **spring == SpringState::Damaged)
+            .map(|(count, _)| count)
+            .eq(self.damaged_groups.iter().copied())
+    }
+
     fn multiply_by(mut self, mult: usize) -> Self {
         self.springs =
             Itertools::intersperse(repeat_n(self.springs, mult), vec![SpringState::Unknown])
@@ -232,7 +438,13 @@ fn parse_spring_row(input: &mut &str) -> PResult<Vec<SpringState>> {
 }
 
 fn parse_spring_groups(input: &mut &str) -> PResult<Vec<usize>> {
-    separated(1.., digit1.parse_to::<usize>(), ',').parse_next(input)
+    // Empty groups would create a damaged block without any damaged springs in the DFA
+    separated(
+        1..,
+        digit1.parse_to::<usize>().verify(|length| *length > 0),
+        ',',
+    )
+    .parse_next(input)
 }
 
 fn parse_spring_record(input: &mut &str) -> PResult<SpringRecord> {
@@ -250,21 +462,156 @@ impl AocTask for Day12 {
     }
 
     fn solution(&self, input: AocStringIter, phase: usize) -> Result<AocSolution, BoxedError> {
-        let records = input.map(|input| {
-            parse_spring_record
-                .parse(&input)
-                .map_err(|err| err.to_string())
-        });
-
-        match phase {
-            1 => records
-                .map_ok(|record| record.compute_arrangements_dfa())
-                .process_results(|iter| iter.sum::<usize>()),
-            2 => records
-                .map_ok(|record| record.multiply_by(5).compute_arrangements_dfa())
-                .process_results(|iter| iter.sum::<usize>()),
-            _ => unimplemented!(),
+        let records = Self::parse_records(input)?;
+        self.total_arrangements(records, phase).solved()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use aoc_framework::AocStringIter;
+    use winnow::Parser;
+
+    use super::{parse_spring_record, Day12, SpringRecord, SpringState};
+
+    const SAMPLE: [(&str, usize, usize); 6] = [
+        ("???.### 1,1,3", 1, 1),
+        (".??..??...?##. 1,1,3", 4, 16384),
+        ("?#?#?#?#?#?#?#? 1,3,1,6", 1, 1),
+        ("????.#...#... 4,1,1", 1, 16),
+        ("????.######..#####. 1,6,5", 4, 2500),
+        ("?###???????? 3,2,1", 10, 506250),
+    ];
+
+    fn record(input: &str) -> SpringRecord {
+        parse_spring_record.parse(input).unwrap()
+    }
+
+    #[test]
+    fn all_methods_agree() {
+        for (input, folded, unfolded) in SAMPLE {
+            for (record, expected) in [
+                (record(input), folded),
+                (record(input).multiply_by(5), unfolded),
+            ] {
+                assert_eq!(record.compute_arrangements_dp(), expected, "{input}");
+                assert_eq!(record.compute_arrangements_dfa(), expected, "{input}");
+                assert_eq!(record.compute_arrangements_memo(), expected, "{input}");
+            }
         }
-        .try_solved()
+    }
+
+    #[test]
+    fn parallel_sum_matches_sequential() {
+        let records = SAMPLE.map(|(input, _, _)| record(input));
+        assert_eq!(Day12::sum_arrangements_par(&records), 21);
+        assert_eq!(Day12::sum_arrangements(&records), 21);
+
+        let unfolded = records.map(|record| record.multiply_by(5));
+        assert_eq!(
+            Day12::sum_arrangements_par(&unfolded),
+            Day12::sum_arrangements(&unfolded)
+        );
+    }
+
+    #[test]
+    fn unfold_multipliers() {
+        for (input, expected) in [
+            ("???.### 1,1,3", [1, 1, 1]),
+            (".??..??...?##. 1,1,3", [4, 32, 256]),
+        ] {
+            for (multiplier, expected) in [1, 2, 3].into_iter().zip(expected) {
+                let record = record(input).multiply_by(multiplier);
+                assert_eq!(record.compute_arrangements_dfa(), expected);
+            }
+
+            let day = Day12::with_unfold(2).unwrap();
+            assert_eq!(day.total_arrangements(vec![record(input)], 2), expected[1]);
+        }
+    }
+
+    #[test]
+    fn zero_unfold() {
+        let error = Day12::with_unfold(0).err().unwrap();
+        assert!(error.to_string().contains("unfolded at least once"));
+    }
+
+    #[test]
+    fn enumerated_arrangements() {
+        for (input, folded, _) in SAMPLE {
+            let record = record(input);
+            let arrangements = record.arrangements().unwrap();
+            assert_eq!(
+                arrangements.len(),
+                record.compute_arrangements_dfa(),
+                "{input}"
+            );
+            assert_eq!(arrangements.len(), folded, "{input}");
+            assert!(arrangements
+                .iter()
+                .flatten()
+                .all(|spring| *spring != SpringState::Unknown));
+        }
+
+        let arrangements = record("?#? 2").arrangements().unwrap();
+        assert_eq!(
+            arrangements,
+            vec![
+                vec![
+                    SpringState::Operational,
+                    SpringState::Damaged,
+                    SpringState::Damaged
+                ],
+                vec![
+                    SpringState::Damaged,
+                    SpringState::Damaged,
+                    SpringState::Operational
+                ],
+            ]
+        );
+
+        // 29 unknown springs after unfolding
+        let unfolded = record(".??..??...?##. 1,1,3").multiply_by(5);
+        assert!(unfolded.arrangements().is_err());
+    }
+
+    #[test]
+    fn both_phases() {
+        let input: AocStringIter =
+            Box::new(SAMPLE.into_iter().map(|(input, _, _)| String::from(input)));
+        assert_eq!(Day12::default().both(input).unwrap(), (21, 525152));
+    }
+
+    #[test]
+    fn empty_group() {
+        assert!(parse_spring_record.parse("???.### 1,0,3").is_err());
+        assert!(parse_spring_record.parse("???.### 0").is_err());
+    }
+
+    #[test]
+    fn groups_longer_than_record() {
+        let too_short = record("??? 2,1");
+        assert!(!too_short.groups_fit());
+        assert_eq!(too_short.compute_arrangements_dfa(), 0);
+        assert_eq!(too_short.compute_arrangements_memo(), 0);
+
+        let exact = record("???? 2,1");
+        assert!(exact.groups_fit());
+        assert_eq!(exact.compute_arrangements_dfa(), 1);
+    }
+
+    #[test]
+    fn trivial_bounds() {
+        let too_damaged = record("#.##?.# 1,2");
+        assert_eq!(too_damaged.trivial_bound(), Some(0));
+        assert_eq!(too_damaged.compute_arrangements_dfa(), 0);
+        assert_eq!(too_damaged.compute_arrangements_memo(), 0);
+
+        let too_few_unknown = record("#.?...? 2,2");
+        assert_eq!(too_few_unknown.trivial_bound(), Some(0));
+        assert_eq!(too_few_unknown.compute_arrangements_dfa(), 0);
+        assert_eq!(too_few_unknown.compute_arrangements_memo(), 0);
+
+        assert_eq!(record("???.### 1,1,3").trivial_bound(), None);
     }
 }
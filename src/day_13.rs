@@ -1,53 +1,134 @@
 use std::path::PathBuf;
 
-use crate::BoxedError;
+use crate::{input::blank_separated_groups, BoxedError};
 use aoc_framework::{traits::*, AocSolution, AocStringIter, AocTask};
-use itertools::Itertools;
 
-pub struct Day13;
+pub struct Day13 {
+    // Number of smudges fixed in phase 2
+    smudges: usize,
+}
+
+impl Default for Day13 {
+    fn default() -> Self {
+        Self { smudges: 1 }
+    }
+}
+
+impl Day13 {
+    #[allow(dead_code)]
+    pub fn with_smudges(smudges: usize) -> Self {
+        Self { smudges }
+    }
+
+    fn total_reflection_value(&self, map: &Map, phase: usize) -> usize {
+        let expected_differences = match phase {
+            1 => 0,
+            2 => self.smudges,
+            _ => unimplemented!(),
+        };
+        map.patterns
+            .iter()
+            .map(|pat| pat.reflection_value(expected_differences))
+            .sum()
+    }
+}
 
 trait SmudgedCmp {
-    fn differences(&self, rhs: &Self) -> usize;
+    // None if the compared lines have different lengths
+    fn checked_differences(&self, rhs: &Self) -> Option<usize>;
+
+    fn differences(&self, rhs: &Self) -> usize {
+        self.checked_differences(rhs)
+            .expect("Patterns should be validated to be rectangular")
+    }
+}
+
+impl SmudgedCmp for str {
+    fn checked_differences(&self, rhs: &Self) -> Option<usize> {
+        (self.chars().count() == rhs.chars().count()).then(|| {
+            self.chars()
+                .zip(rhs.chars())
+                .filter(|(l, r)| l != r)
+                .count()
+        })
+    }
+}
+
+impl SmudgedCmp for [u8] {
+    fn checked_differences(&self, rhs: &Self) -> Option<usize> {
+        (self.len() == rhs.len()).then(|| self.iter().zip(rhs).filter(|(l, r)| l != r).count())
+    }
 }
 
 impl SmudgedCmp for String {
-    fn differences(&self, rhs: &Self) -> usize {
-        self.chars()
-            .zip(rhs.chars())
-            .filter(|(l, r)| l != r)
-            .count()
+    fn checked_differences(&self, rhs: &Self) -> Option<usize> {
+        self.as_str().checked_differences(rhs)
     }
 }
 
+// Orientation of the reflection line, horizontal lines are found between rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    Horizontal,
+    Vertical,
+}
+
 #[derive(Debug)]
 struct Pattern {
-    data: Vec<String>,
+    rows: Vec<String>,
+    // Transposed rows, computed once since every reflection check goes through them
+    cols: Vec<String>,
     width: usize,
 }
 
 impl Pattern {
-    fn new(data: Vec<String>) -> Self {
-        let width = data.first().map(|row| row.len()).unwrap_or_default();
-        Self { data, width }
+    fn new(rows: Vec<String>) -> Result<Self, BoxedError> {
+        let width = rows
+            .first()
+            .map(|row| row.chars().count())
+            .unwrap_or_default();
+        let mut cols = vec![String::with_capacity(rows.len()); width];
+        for row in rows.iter() {
+            for (col, chr) in cols.iter_mut().zip(row.chars()) {
+                col.push(chr);
+            }
+        }
+        let pattern = Self { rows, cols, width };
+        pattern.validate()?;
+        Ok(pattern)
     }
 
-    fn rows(&self) -> Vec<String> {
-        self.data.clone()
+    // Columns would silently be cut short by ragged rows
+    fn validate(&self) -> Result<(), BoxedError> {
+        match self
+            .rows
+            .iter()
+            .position(|row| row.chars().count() != self.width)
+        {
+            Some(idx) => Err(format!(
+                "Pattern row {idx} is {} wide, expected {}",
+                self.rows[idx].chars().count(),
+                self.width
+            )
+            .into()),
+            None => Ok(()),
+        }
     }
 
-    fn cols(&self) -> Vec<String> {
-        (0..self.width)
-            .map(|col| {
-                self.data
-                    .iter()
-                    .flat_map(|row| row.chars().nth(col))
-                    .collect()
-            })
-            .collect()
+    fn rows(&self) -> &[String] {
+        &self.rows
     }
 
-    fn reflection_value(&self, expected_differences: usize) -> usize {
-        for (lines, multiplier) in [(self.rows(), 100), (self.cols(), 1)] {
+    fn cols(&self) -> &[String] {
+        &self.cols
+    }
+
+    fn all_reflections(&self, expected_differences: usize) -> Vec<(Axis, usize)> {
+        let mut reflections = vec![];
+        for (lines, axis) in [
+            (self.rows(), Axis::Horizontal),
+            (self.cols(), Axis::Vertical),
+        ] {
             for i in 1..lines.len() {
                 let before = &lines[0..i];
                 let after = &lines[i..lines.len()];
@@ -58,11 +139,45 @@ impl Pattern {
                     .map(|(b, a)| b.differences(a))
                     .sum::<usize>();
                 if differences == expected_differences {
-                    return i * multiplier;
+                    reflections.push((axis, i));
                 }
             }
         }
-        0
+        reflections
+    }
+
+    // The (row, col) of the single differing cell along the first reflection with one difference.
+    // Flipping either of the two mismatching cells fixes the reflection, the one before the line
+    // is returned
+    #[allow(dead_code)]
+    fn smudge_position(&self) -> Option<(usize, usize)> {
+        let (axis, i) = *self.all_reflections(1).first()?;
+        let lines = match axis {
+            Axis::Horizontal => self.rows(),
+            Axis::Vertical => self.cols(),
+        };
+        let (line, offset) = (0..i)
+            .rev()
+            .zip(i..lines.len())
+            .find_map(|(before, after)| {
+                lines[before]
+                    .chars()
+                    .zip(lines[after].chars())
+                    .position(|(b, a)| b != a)
+                    .map(|offset| (before, offset))
+            })?;
+        match axis {
+            Axis::Horizontal => Some((line, offset)),
+            Axis::Vertical => Some((offset, line)),
+        }
+    }
+
+    fn reflection_value(&self, expected_differences: usize) -> usize {
+        match self.all_reflections(expected_differences).first() {
+            Some((Axis::Horizontal, i)) => i * 100,
+            Some((Axis::Vertical, i)) => *i,
+            None => 0,
+        }
     }
 }
 
@@ -71,13 +186,11 @@ struct Map {
 }
 
 impl Map {
-    fn new(input: AocStringIter) -> Self {
-        let patterns = input
-            .group_by(|line| !line.is_empty())
-            .into_iter()
-            .filter_map(|(not_empty, group)| not_empty.then(|| Pattern::new(group.collect())))
-            .collect::<Vec<_>>();
-        Self { patterns }
+    fn new(input: AocStringIter) -> Result<Self, BoxedError> {
+        let patterns = blank_separated_groups(input)
+            .map(Pattern::new)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { patterns })
     }
 }
 
@@ -87,16 +200,125 @@ impl AocTask for Day13 {
     }
 
     fn solution(&self, input: AocStringIter, phase: usize) -> Result<AocSolution, BoxedError> {
-        let expected_differences = match phase {
-            1 => 0,
-            2 => 1,
-            _ => unimplemented!(),
-        };
-        let map = Map::new(input);
-        map.patterns
-            .iter()
-            .map(|pat| pat.reflection_value(expected_differences))
-            .sum::<usize>()
-            .solved()
+        let map = Map::new(input)?;
+        self.total_reflection_value(&map, phase).solved()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate test;
+
+    use test::Bencher;
+
+    use super::{Axis, Day13, Map, Pattern, SmudgedCmp};
+
+    fn pattern(input: &str) -> Pattern {
+        Pattern::new(input.lines().map(String::from).collect()).unwrap()
+    }
+
+    #[test]
+    fn multiple_reflections() {
+        let pattern = pattern(
+            "#..#
+#..#",
+        );
+        assert_eq!(
+            pattern.all_reflections(0),
+            vec![(Axis::Horizontal, 1), (Axis::Vertical, 2)]
+        );
+        assert_eq!(pattern.reflection_value(0), 100);
+    }
+
+    #[test]
+    fn two_smudges() {
+        let input = "###.
+.#.#
+#.#.";
+        let pattern = pattern(input);
+        assert!(pattern.all_reflections(0).is_empty());
+        assert!(pattern.all_reflections(1).is_empty());
+        assert_eq!(pattern.all_reflections(2), vec![(Axis::Vertical, 1)]);
+
+        let map = Map::new(Box::new(input.lines().map(String::from))).unwrap();
+        assert_eq!(Day13::default().total_reflection_value(&map, 2), 0);
+        assert_eq!(Day13::with_smudges(2).total_reflection_value(&map, 2), 1);
+    }
+
+    fn checkerboard(size: usize) -> Pattern {
+        Pattern::new(
+            (0..size)
+                .map(|row| {
+                    (0..size)
+                        .map(|col| if (row + col) % 3 == 0 { '#' } else { '.' })
+                        .collect()
+                })
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    #[bench]
+    fn transpose_100x100(b: &mut Bencher) {
+        b.iter(|| checkerboard(100).cols().len());
+    }
+
+    #[bench]
+    fn reflection_100x100(b: &mut Bencher) {
+        let pattern = checkerboard(100);
+        b.iter(|| pattern.reflection_value(1));
+    }
+
+    #[test]
+    fn ragged_pattern() {
+        let rows = vec!["#.#".to_string(), "#.".to_string()];
+        assert!(rows[0].checked_differences(&rows[1]).is_none());
+        assert_eq!(rows[0].checked_differences(&rows[0]), Some(0));
+        assert!(Pattern::new(rows).is_err());
+
+        let input = "#.#\n#.\n\n##\n##";
+        assert!(Map::new(Box::new(input.lines().map(String::from))).is_err());
+    }
+
+    #[test]
+    fn smudge_positions() {
+        let first = pattern(
+            "#.##..##.
+..#.##.#.
+##......#
+##......#
+..#.##.#.
+..##..##.
+#.#.##.#.",
+        );
+        let second = pattern(
+            "#...##..#
+#....#..#
+..##..###
+#####.##.
+#####.##.
+..##..###
+#....#..#",
+        );
+        assert_eq!(first.smudge_position(), Some((0, 0)));
+        assert_eq!(second.smudge_position(), Some((0, 4)));
+        assert_eq!(first.reflection_value(1), 300);
+        assert_eq!(second.reflection_value(1), 100);
+
+        // A vertical reflection with a smudge in the 3rd row
+        let vertical = pattern(
+            "#..#
+.##.
+#.##",
+        );
+        assert_eq!(vertical.smudge_position(), Some((2, 1)));
+    }
+
+    #[test]
+    fn slice_differences() {
+        assert_eq!("#.##..##.".differences("..##..###"), 2);
+        assert_eq!("#.##".checked_differences("#.#"), None);
+        assert_eq!(b"#.##"[..].differences(&b"#..#"[..]), 1);
+        assert_eq!(b"#.##"[..].checked_differences(&b"#."[..]), None);
     }
 }
@@ -1,4 +1,9 @@
-use std::path::PathBuf;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt::Display,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::PathBuf,
+};
 
 use crate::BoxedError;
 use aoc_framework::{traits::*, AocSolution, AocStringIter, AocTask};
@@ -6,37 +11,63 @@ use ndarray::{s, Array1, Array2};
 
 pub struct Day14;
 
-struct Platform {
+pub struct Platform {
     data: Array2<char>,
 }
 
-enum SettleDir {
+#[derive(Debug, Clone, Copy)]
+pub enum SettleDir {
     North,
     East,
     South,
     West,
 }
 
+const SPIN_ORDER: [SettleDir; 4] = [
+    SettleDir::North,
+    SettleDir::West,
+    SettleDir::South,
+    SettleDir::East,
+];
+
+impl Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in self.data.rows() {
+            writeln!(f, "{}", row.iter().collect::<String>())?;
+        }
+        Ok(())
+    }
+}
+
 impl Platform {
-    fn new(input: AocStringIter) -> Self {
+    pub fn new(input: AocStringIter) -> Result<Self, BoxedError> {
         let data: Vec<String> = input.collect();
+        // Anything else would be treated as a fixed rock by tilt()
+        if let Some((y, x, chr)) = data.iter().enumerate().find_map(|(y, row)| {
+            row.chars()
+                .enumerate()
+                .find(|(_, chr)| !matches!(chr, '.' | 'O' | '#'))
+                .map(|(x, chr)| (y, x, chr))
+        }) {
+            return Err(format!("Unexpected character '{chr}' at ({x}, {y})").into());
+        }
         let width = data.first().map_or(0, String::len);
         let height = data.len();
 
         let array = Array2::from_shape_vec(
-            (width, height),
+            (height, width),
             data.into_iter()
                 .fold(Vec::with_capacity(width * height), |mut acc, s| {
                     acc.extend(s.chars());
                     acc
                 }),
-        )
-        .expect("Input should be valid");
+        )?;
 
-        Self { data: array }
+        Ok(Self { data: array })
     }
 
-    fn settle(&mut self, dir: &SettleDir) {
+    // Moves all round rocks as far as they go in the given direction
+    pub fn tilt(&mut self, dir: SettleDir) {
         let slice = |idx| match dir {
             SettleDir::North => s![0.., idx],
             SettleDir::East => s![idx, 0..;-1],
@@ -44,8 +75,13 @@ impl Platform {
             SettleDir::West => s![idx, 0..],
         };
 
-        // Platforms are square
-        for idx in 0..self.data.dim().0 {
+        // North/South settle the columns, East/West settle the rows
+        let (height, width) = self.data.dim();
+        let lines = match dir {
+            SettleDir::North | SettleDir::South => width,
+            SettleDir::East | SettleDir::West => height,
+        };
+        for idx in 0..lines {
             let mut row = self.data.slice_mut(slice(idx));
             let mut free_spot = 0;
             for y in 0..row.dim() {
@@ -62,37 +98,98 @@ impl Platform {
         }
     }
 
-    fn spin_cycles(&mut self, cycles: usize) {
-        let mut dirs = [
-            SettleDir::North,
-            SettleDir::West,
-            SettleDir::South,
-            SettleDir::East,
-        ]
-        .iter()
-        .cycle()
-        .take(4 * cycles);
+    // The grid as it is after the last tilt
+    #[allow(dead_code)]
+    pub fn current_grid(&self) -> &Array2<char> {
+        &self.data
+    }
+
+    // Only round rocks move, so their positions are enough to tell states apart
+    fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.data
+            .indexed_iter()
+            .filter(|(_, chr)| **chr == 'O')
+            .for_each(|(pos, _)| pos.hash(&mut hasher));
+        hasher.finish()
+    }
+
+    // Returns the rotation at which the first repeated state was seen and the cycle length
+    fn spin_cycles(&mut self, cycles: usize) -> Option<(usize, usize)> {
+        self.spin_cycles_with(cycles, &SPIN_ORDER)
+    }
+
+    fn spin_cycles_with(&mut self, cycles: usize, order: &[SettleDir]) -> Option<(usize, usize)> {
+        // Nothing to spin, the platform stays as it is
+        if order.is_empty() {
+            return None;
+        }
+        let total_rotations = order.len() * cycles;
+        let mut dirs = order.iter().cycle().take(total_rotations);
 
         let mut rotation = 0;
-        let mut history = Vec::new();
+        // (Fingerprint, position in the order) -> rotation after which it was seen.
+        // A state only repeats the rotations after it if the next direction is the same
+        let mut history = HashMap::new();
+        // A fingerprint can collide, so a repeat is only accepted once the grid
+        // cloned at the repeat comes back after another cycle length
+        let mut candidate: Option<(Array2<char>, usize, usize)> = None;
         let mut min_rotations_required = 0;
-        history.push(self.data.clone());
+        let mut cycle = None;
+        history.insert((self.fingerprint(), 0), rotation);
         for dir in dirs.by_ref() {
-            self.settle(dir);
+            self.tilt(*dir);
             rotation += 1;
-            let new_state = self.data.clone();
-            if history.contains(&new_state) {
-                let cycle_start = history.iter().position(|h| h == new_state).unwrap();
-                let cycle_lenght = rotation - cycle_start;
-                let rotations_left = 4 * cycles - rotation;
-                min_rotations_required = rotations_left % cycle_lenght;
-                break;
+            if let Some((grid, cycle_start, cycle_lenght)) = &candidate {
+                if rotation - cycle_start < 2 * cycle_lenght {
+                    continue;
+                }
+                if self.data == *grid {
+                    let rotations_left = total_rotations - rotation;
+                    min_rotations_required = rotations_left % cycle_lenght;
+                    cycle = Some((*cycle_start, *cycle_lenght));
+                    break;
+                }
+                candidate = None;
+            }
+            match history.entry((self.fingerprint(), rotation % order.len())) {
+                Entry::Occupied(mut entry) => {
+                    let cycle_start = entry.insert(rotation);
+                    candidate = Some((self.data.clone(), cycle_start, rotation - cycle_start));
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(rotation);
+                }
             }
-            history.push(new_state);
         }
 
         dirs.take(min_rotations_required)
-            .for_each(|dir| self.settle(dir));
+            .for_each(|dir| self.tilt(*dir));
+        cycle
+    }
+
+    // North load after each of the first n spin cycles. Once a state repeats, the rest of
+    // the series is copied from the cycle, the platform is left in the repeated state
+    #[allow(dead_code)]
+    fn load_series(&mut self, cycles: usize) -> Vec<usize> {
+        let mut loads = Vec::with_capacity(cycles);
+        // Fingerprint -> spin cycle after which it was seen
+        let mut history = HashMap::new();
+        history.insert(self.fingerprint(), 0);
+        for cycle in 1..=cycles {
+            SPIN_ORDER.iter().for_each(|dir| self.tilt(*dir));
+            loads.push(self.load());
+            if let Some(cycle_start) = history.insert(self.fingerprint(), cycle) {
+                let cycle_length = cycle - cycle_start;
+                // loads[i] is the load after spin cycle i + 1
+                let repeated = (cycle + 1..=cycles)
+                    .map(|next| loads[cycle_start + (next - cycle_start - 1) % cycle_length])
+                    .collect::<Vec<_>>();
+                loads.extend(repeated);
+                break;
+            }
+        }
+        loads
     }
 
     fn load(&self) -> usize {
@@ -108,12 +205,147 @@ impl AocTask for Day14 {
     }
 
     fn solution(&self, input: AocStringIter, phase: usize) -> Result<AocSolution, BoxedError> {
-        let mut platform = Platform::new(input);
+        let mut platform = Platform::new(input)?;
         match phase {
-            1 => platform.settle(&SettleDir::North),
-            2 => platform.spin_cycles(10_usize.pow(9)),
+            1 => platform.tilt(SettleDir::North),
+            2 => {
+                platform.spin_cycles(10_usize.pow(9));
+            }
             _ => unimplemented!(),
         }
         platform.load().solved()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use itertools::Itertools;
+
+    use super::{Platform, SettleDir, SPIN_ORDER};
+
+    const SAMPLE: &str = "O....#....
+O.OO#....#
+.....##...
+OO.#O....O
+.O.....O#.
+O.#..O.#.#
+..O..#O..O
+.......O..
+#....###..
+#OO..#....";
+
+    fn platform(input: &str) -> Platform {
+        Platform::new(Box::new(input.lines().map(String::from))).unwrap()
+    }
+
+    #[test]
+    fn non_square_platform() {
+        let mut tilted = platform(
+            ".O.#O
+O.O..
+#..O.",
+        );
+        assert_eq!(tilted.data.dim(), (3, 5));
+        tilted.tilt(SettleDir::North);
+        let expected = platform(
+            "OOO#O
+...O.
+#....",
+        );
+        assert_eq!(tilted.data, expected.data);
+        assert_eq!(tilted.load(), 14);
+    }
+
+    #[test]
+    fn sample_cycle() {
+        let mut platform = platform(SAMPLE);
+        assert_eq!(platform.spin_cycles(10_usize.pow(9)), Some((10, 28)));
+        assert_eq!(platform.load(), 64);
+    }
+
+    #[test]
+    fn sample_load_series() {
+        let series = platform(SAMPLE).load_series(30);
+        assert_eq!(series.len(), 30);
+        assert_eq!(&series[..3], &[87, 69, 69]);
+        // The sample repeats every 7 spin cycles starting from the 3rd one
+        for idx in 2..series.len() - 7 {
+            assert_eq!(series[idx], series[idx + 7]);
+        }
+        for (idx, load) in series.iter().enumerate() {
+            let mut platform = platform(SAMPLE);
+            platform.spin_cycles(idx + 1);
+            assert_eq!(platform.load(), *load);
+        }
+    }
+
+    #[test]
+    fn tilt_west_then_south() {
+        let mut platform = platform(SAMPLE);
+        platform.tilt(SettleDir::West);
+        platform.tilt(SettleDir::South);
+        assert_eq!(platform.current_grid().dim(), (10, 10));
+        assert_eq!(
+            platform.to_string(),
+            ".....#....
+O...#....#
+O....##...
+O..#......
+O.O.....#.
+O.#..O.#.#
+OO...#....
+OO....OO..
+#O...###..
+#OOOO#....
+"
+        );
+    }
+
+    #[test]
+    fn unexpected_character() {
+        let input = "O..\n.X#\n...";
+        let error = Platform::new(Box::new(input.lines().map(String::from)));
+        assert_eq!(
+            error.err().map(|err| err.to_string()),
+            Some("Unexpected character 'X' at (1, 1)".to_string())
+        );
+    }
+
+    #[test]
+    fn distinct_fingerprints() {
+        let mut platform = platform(SAMPLE);
+        let mut states = vec![(platform.current_grid().clone(), platform.fingerprint())];
+        // The sample repeats after 38 rotations
+        for dir in SPIN_ORDER.iter().cycle().take(38) {
+            platform.tilt(*dir);
+            states.push((platform.current_grid().clone(), platform.fingerprint()));
+        }
+        for ((grid_a, fingerprint_a), (grid_b, fingerprint_b)) in states.iter().tuple_combinations()
+        {
+            assert_eq!(grid_a == grid_b, fingerprint_a == fingerprint_b);
+        }
+    }
+
+    #[test]
+    fn reversed_spin_order() {
+        let mut platform = platform(SAMPLE);
+        let reversed = [
+            SettleDir::East,
+            SettleDir::South,
+            SettleDir::West,
+            SettleDir::North,
+        ];
+        assert_eq!(
+            platform.spin_cycles_with(10_usize.pow(9), &reversed),
+            Some((12, 4))
+        );
+        assert_eq!(platform.load(), 111);
+    }
+
+    #[test]
+    fn empty_spin_order() {
+        let mut platform = platform(SAMPLE);
+        assert_eq!(platform.spin_cycles_with(10, &[]), None);
+        assert_eq!(platform.to_string(), format!("{SAMPLE}\n"));
+    }
+}
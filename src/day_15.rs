@@ -1,50 +1,142 @@
-use std::path::PathBuf;
+use std::{fmt::Display, path::PathBuf};
 
 use crate::BoxedError;
 use aoc_framework::{traits::*, AocSolution, AocStringIter, AocTask};
 use itertools::Itertools;
+use winnow::{
+    ascii::{alphanumeric1, digit1},
+    combinator::{empty, fail, opt, separated},
+    dispatch,
+    token::any,
+    PResult, Parser,
+};
 
 pub struct Day15;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Remove,
+    Insert,
+}
+
+type Step = (String, Op, Option<u8>);
+
+fn parse_op(input: &mut &str) -> PResult<Op> {
+    dispatch! {any;
+        '-' => empty.value(Op::Remove),
+        '=' => empty.value(Op::Insert),
+        _ => fail,
+    }
+    .parse_next(input)
+}
+
+fn parse_step(input: &mut &str) -> PResult<Step> {
+    (
+        alphanumeric1.map(String::from),
+        parse_op,
+        opt(digit1.parse_to::<u8>()),
+    )
+        .parse_next(input)
+}
+
+fn parse_steps(input: &mut &str) -> PResult<Vec<Step>> {
+    separated(0.., parse_step, ',').parse_next(input)
+}
+
+// The HASH algorithm is defined over bytes, wrapping u8 arithmetic does the modulo 256
+pub fn aoc_hash(input: &[u8]) -> u8 {
+    input
+        .iter()
+        .fold(0u8, |hash, byte| hash.wrapping_add(*byte).wrapping_mul(17))
+}
+
 fn hash(input: &str) -> usize {
-    let mut hash = 0;
-    for c in input.chars() {
-        hash += c as usize;
-        hash *= 17;
-        hash %= 256;
+    aoc_hash(input.as_bytes()) as usize
+}
+
+// The HASHMAP procedure, lenses are kept in the box picked by the hash of their label
+struct LensBoxes {
+    boxes: Vec<Vec<(String, usize)>>,
+}
+
+impl Default for LensBoxes {
+    fn default() -> Self {
+        Self {
+            boxes: vec![vec![]; 256],
+        }
+    }
+}
+
+impl Display for LensBoxes {
+    // Same format as the puzzle's worked example, only non-empty boxes are shown
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (box_idx, box_contents) in self.boxes.iter().enumerate() {
+            if box_contents.is_empty() {
+                continue;
+            }
+            let lenses = box_contents
+                .iter()
+                .map(|(label, focal)| format!("[{label} {focal}]"))
+                .join(" ");
+            writeln!(f, "Box {box_idx}: {lenses}")?;
+        }
+        Ok(())
     }
-    hash
 }
 
-fn focusing_power(lenses: Vec<[&str; 3]>) -> usize {
-    let mut boxmap: Vec<Vec<(&str, usize)>> = vec![vec![]; 256];
-    for lens in lenses {
-        match lens {
-            [label, "-", _] => boxmap[hash(label)].retain(|e| e.0 != label),
-            [label, "=", num] => {
-                let parsed_num = num.parse().expect("Focal lenght should be valid");
-                let bucket = &mut boxmap[hash(label)];
-                if let Some(idx) = bucket.iter().position(|e| e.0 == label) {
-                    bucket[idx] = (label, parsed_num);
-                } else {
-                    bucket.push((label, parsed_num));
+impl LensBoxes {
+    #[allow(dead_code)]
+    fn boxes(&self) -> &[Vec<(String, usize)>] {
+        &self.boxes
+    }
+
+    fn from_steps(steps: Vec<Step>) -> Result<Self, BoxedError> {
+        let mut boxes = Self::default();
+        for (label, op, focal) in steps {
+            match (op, focal) {
+                (Op::Remove, _) => boxes.remove(&label),
+                (Op::Insert, Some(focal @ 1..=9)) => boxes.insert(&label, focal as usize),
+                (Op::Insert, Some(focal)) => {
+                    return Err(format!(
+                        "Focal length of lens {label} should be between 1 and 9, got {focal}"
+                    )
+                    .into())
+                }
+                (Op::Insert, None) => {
+                    return Err(format!("Missing focal length for lens {label}").into())
                 }
             }
-            _ => {}
         }
+        Ok(boxes)
     }
 
-    boxmap
-        .iter()
-        .enumerate()
-        .map(|(box_idx, box_contents)| {
-            box_contents
-                .iter()
-                .enumerate()
-                .map(|(slot_idx, lens)| (box_idx + 1) * (slot_idx + 1) * lens.1)
-                .sum::<usize>()
-        })
-        .sum()
+    // Replaces the focal length of a lens with the same label, or adds the lens to the back
+    fn insert(&mut self, label: &str, focal: usize) {
+        let bucket = &mut self.boxes[hash(label)];
+        if let Some(idx) = bucket.iter().position(|e| e.0 == label) {
+            bucket[idx].1 = focal;
+        } else {
+            bucket.push((label.to_string(), focal));
+        }
+    }
+
+    fn remove(&mut self, label: &str) {
+        self.boxes[hash(label)].retain(|e| e.0 != label);
+    }
+
+    fn focusing_power(&self) -> usize {
+        self.boxes
+            .iter()
+            .enumerate()
+            .map(|(box_idx, box_contents)| {
+                box_contents
+                    .iter()
+                    .enumerate()
+                    .map(|(slot_idx, lens)| (box_idx + 1) * (slot_idx + 1) * lens.1)
+                    .sum::<usize>()
+            })
+            .sum()
+    }
 }
 
 impl AocTask for Day15 {
@@ -54,23 +146,98 @@ impl AocTask for Day15 {
 
     fn solution(&self, mut input: AocStringIter, phase: usize) -> Result<AocSolution, BoxedError> {
         let data = input.next().unwrap_or_default();
-        let lense_pat = regex::Regex::new(r#"(\w+)([-=])(\d?)"#)?;
-        let lenses = data
-            .split(',')
-            .filter_map(|string| {
-                let (_, lens) = lense_pat.captures(string)?.extract::<3>();
-                Some(lens)
-            })
-            .collect_vec();
 
         match phase {
-            1 => lenses
-                .iter()
-                .map(|lens| hash(&lens.join("")))
-                .sum::<usize>(),
-            2 => focusing_power(lenses),
+            1 => data.split(',').map(hash).sum::<usize>(),
+            2 => {
+                let steps = parse_steps.parse(&data).map_err(|err| err.to_string())?;
+                LensBoxes::from_steps(steps)?.focusing_power()
+            }
             _ => unimplemented!(),
         }
         .solved()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use winnow::Parser;
+
+    use super::{aoc_hash, parse_step, parse_steps, LensBoxes, Op};
+
+    const SAMPLE: &str = "rn=1,cm-,qp=3,cm=2,qp-,pc=4,ot=9,ab=5,pc-,pc=6,ot=7";
+
+    #[test]
+    fn steps() {
+        assert_eq!(
+            parse_step.parse("rn=1").unwrap(),
+            ("rn".to_string(), Op::Insert, Some(1))
+        );
+        assert_eq!(
+            parse_step.parse("cm-").unwrap(),
+            ("cm".to_string(), Op::Remove, None)
+        );
+        assert_eq!(
+            parse_steps.parse("rn=1,cm-,qp=3,pc-").unwrap(),
+            vec![
+                ("rn".to_string(), Op::Insert, Some(1)),
+                ("cm".to_string(), Op::Remove, None),
+                ("qp".to_string(), Op::Insert, Some(3)),
+                ("pc".to_string(), Op::Remove, None),
+            ]
+        );
+        assert!(parse_step.parse("rn*1").is_err());
+    }
+
+    #[test]
+    fn hash_bytes() {
+        assert_eq!(aoc_hash(b"HASH"), 52);
+        assert_eq!(aoc_hash(b"rn=1"), 30);
+        assert_eq!(aoc_hash(b""), 0);
+    }
+
+    #[test]
+    fn sample_boxes() {
+        let mut boxes = LensBoxes::default();
+        boxes.insert("rn", 1);
+        boxes.remove("cm");
+        boxes.insert("qp", 3);
+        assert_eq!(boxes.boxes[0], vec![("rn".to_string(), 1)]);
+        assert_eq!(boxes.boxes[1], vec![("qp".to_string(), 3)]);
+
+        let boxes = LensBoxes::from_steps(parse_steps.parse(SAMPLE).unwrap()).unwrap();
+        let lens = |label: &str, focal| (label.to_string(), focal);
+        assert_eq!(boxes.boxes[0], vec![lens("rn", 1), lens("cm", 2)]);
+        assert!(boxes.boxes[1].is_empty());
+        assert_eq!(
+            boxes.boxes[3],
+            vec![lens("ot", 7), lens("ab", 5), lens("pc", 6)]
+        );
+        assert_eq!(boxes.focusing_power(), 145);
+    }
+
+    #[test]
+    fn final_arrangement() {
+        let boxes = LensBoxes::from_steps(parse_steps.parse(SAMPLE).unwrap()).unwrap();
+        let lens = |label: &str, focal| (label.to_string(), focal);
+        assert_eq!(boxes.boxes()[0], vec![lens("rn", 1), lens("cm", 2)]);
+        assert_eq!(
+            boxes.boxes()[3],
+            vec![lens("ot", 7), lens("ab", 5), lens("pc", 6)]
+        );
+        assert_eq!(
+            boxes.to_string(),
+            "Box 0: [rn 1] [cm 2]\nBox 3: [ot 7] [ab 5] [pc 6]\n"
+        );
+    }
+
+    #[test]
+    fn invalid_focal_lengths() {
+        for input in ["ab=0", "ab=10", "ab="] {
+            let steps = parse_steps.parse(input).unwrap();
+            assert!(LensBoxes::from_steps(steps).is_err(), "{input}");
+        }
+        // Doesn't fit into a u8
+        assert!(parse_steps.parse("ab=300").is_err());
+    }
+}
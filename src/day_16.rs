@@ -1,78 +1,129 @@
 use std::{
-    collections::{HashMap, VecDeque},
-    iter::repeat,
+    array,
+    collections::{HashMap, HashSet, VecDeque},
+    iter::{repeat, Take},
     path::PathBuf,
 };
 
-use crate::BoxedError;
+use crate::{
+    geometry::{Direction, Point},
+    grid, BoxedError,
+};
 use aoc_framework::{traits::*, AocSolution, AocStringIter, AocTask};
+use itertools::Itertools;
+use rayon::prelude::*;
 
 pub struct Day16;
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
-enum Direction {
-    North,
-    East,
-    South,
-    West,
+use Direction::*;
+
+enum Tile {
+    Empty,
+    VerticalSplitter,
+    HorizontalSplitter,
+    SlashMirror,
+    BackslashMirror,
+    // Splits the beam in all four directions
+    CrossSplitter,
+    // Stops the beam
+    Absorber,
 }
 
-use Direction::*;
+impl TryFrom<char> for Tile {
+    type Error = BoxedError;
 
-impl Direction {
-    fn horizontal(&self) -> bool {
-        matches!(self, East | West)
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        Ok(match value {
+            '.' => Self::Empty,
+            '|' => Self::VerticalSplitter,
+            '-' => Self::HorizontalSplitter,
+            '/' => Self::SlashMirror,
+            '\\' => Self::BackslashMirror,
+            '+' => Self::CrossSplitter,
+            'o' => Self::Absorber,
+            _ => return Err(format!("Unexpected tile: {value}").into()),
+        })
     }
+}
 
-    fn opposite(&self) -> Self {
+impl Tile {
+    // Directions of the beams leaving the tile when entered in the given direction
+    fn outgoing(&self, direction: &Direction) -> Outgoing {
         match self {
-            North => South,
-            East => West,
-            South => North,
-            West => East,
+            Tile::Empty => [direction.clone()].into(),
+            Tile::VerticalSplitter => match direction.horizontal() {
+                true => [North, South].into(),
+                false => [direction.clone()].into(),
+            },
+            Tile::HorizontalSplitter => match direction.horizontal() {
+                true => [direction.clone()].into(),
+                false => [East, West].into(),
+            },
+            Tile::SlashMirror => [direction.reflect_slash()].into(),
+            Tile::BackslashMirror => [direction.reflect_backslash()].into(),
+            Tile::CrossSplitter => [North, East, South, West].into(),
+            Tile::Absorber => [].into(),
         }
     }
 }
 
+// A tile splits a beam into four at most, so the outgoing beams fit into a fixed array
+struct Outgoing {
+    directions: [Direction; 4],
+    len: usize,
+}
+
+impl Outgoing {
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl<const N: usize> From<[Direction; N]> for Outgoing {
+    fn from(value: [Direction; N]) -> Self {
+        assert!(N <= 4, "A tile should have at most four outgoing beams");
+        // The unused slots are never read
+        let mut directions = [North, North, North, North];
+        directions
+            .iter_mut()
+            .zip(value)
+            .for_each(|(slot, direction)| *slot = direction);
+        Self { directions, len: N }
+    }
+}
+
+impl IntoIterator for Outgoing {
+    type Item = Direction;
+    type IntoIter = Take<array::IntoIter<Direction, 4>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.directions.into_iter().take(self.len)
+    }
+}
+
 #[derive(Clone)]
 struct Beam {
-    head: (i32, i32),
+    head: Point,
     direction: Direction,
 }
 
 impl Beam {
     fn new(x: i32, y: i32, dir: Direction) -> Self {
         Self {
-            head: (x, y),
+            head: Point::new(x, y),
             direction: dir,
         }
     }
 
-    fn next(mut self, tiles: &mut Tiles, hit_mirror: bool) -> Option<Self> {
-        tiles.energize(&self);
-
-        let offset = match self.direction {
-            North => (0, -1),
-            East => (1, 0),
-            South => (0, 1),
-            West => (-1, 0),
-        };
-        let new_head = (self.head.0 + offset.0, self.head.1 + offset.1);
-        if new_head.0 < 0
-            || new_head.1 < 0
-            || new_head.0 >= tiles.width
-            || new_head.1 >= tiles.height
-        {
+    fn next(mut self, grid: &Grid, state: &mut BeamState) -> Option<Self> {
+        let new_head = self.head + self.direction.offset();
+        if !grid.contains(new_head) {
             return None;
         }
 
         self.head = new_head;
 
-        if hit_mirror {
-            tiles.is_new_reflection(&self).then_some(self)
-        } else {
-            Some(self)
-        }
+        state.is_new_visit(&self).then_some(self)
     }
 
     fn turn(mut self, dir: Direction) -> Self {
@@ -81,150 +132,508 @@ impl Beam {
     }
 }
 
-struct Tiles {
-    data: Vec<Vec<char>>,
-    // Holds information about the directions form which the mirrors have been energized already
-    energized_mirrors: HashMap<(i32, i32), Vec<Direction>>,
-    energized_tiles: Vec<Vec<bool>>,
+// The read-only part of the contraption, shared between simulations
+struct Grid {
+    tiles: grid::Grid<Tile>,
     width: i32,
     height: i32,
 }
 
-impl Tiles {
-    fn new(input: AocStringIter) -> Self {
-        let data: Vec<Vec<_>> = input.map(|str| str.chars().collect()).collect();
-        let height = data.len();
-        let width = data
-            .first()
-            .expect("Tiles should contain at least one row")
-            .len();
+// Energization state of a single simulation
+struct BeamState {
+    // Tiles and the directions in which beams have entered them already, a beam repeating
+    // one of these would only retrace an already simulated path
+    visited: HashSet<(Point, Direction)>,
+    energized_tiles: Vec<Vec<bool>>,
+    splits: usize,
+    loops_cut: usize,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct BeamStats {
+    energized: usize,
+    // Beams entering a splitter from its flat side
+    splits: usize,
+    // Beams dropped for retracing an already simulated path
+    loops_cut: usize,
+}
+
+impl BeamState {
+    fn new(grid: &Grid) -> Self {
         Self {
-            data,
-            width: width as i32,
-            height: height as i32,
-            energized_mirrors: HashMap::new(),
-            energized_tiles: vec![vec![false; width]; height],
+            visited: HashSet::new(),
+            energized_tiles: vec![vec![false; grid.width as usize]; grid.height as usize],
+            splits: 0,
+            loops_cut: 0,
         }
     }
 
-    fn reset(&mut self) {
-        self.energized_mirrors.clear();
-        self.energized_tiles
-            .iter_mut()
-            .for_each(|row| row.fill(false));
+    fn energize(&mut self, beam: &Beam) {
+        self.energized_tiles[beam.head.y as usize][beam.head.x as usize] = true
     }
 
-    fn tile(&self, beam: &Beam) -> char {
-        self.data[beam.head.1 as usize][beam.head.0 as usize]
+    fn is_new_visit(&mut self, beam: &Beam) -> bool {
+        let new = self.visited.insert((beam.head, beam.direction.clone()));
+        if !new {
+            self.loops_cut += 1;
+        }
+        new
     }
+}
 
-    fn energize(&mut self, beam: &Beam) {
-        self.energized_tiles[beam.head.1 as usize][beam.head.0 as usize] = true
+impl Grid {
+    fn new(input: AocStringIter) -> Result<Self, BoxedError> {
+        let tiles = grid::Grid::from_chars(input).try_map(Tile::try_from)?;
+        assert!(tiles.height() > 0, "Tiles should contain at least one row");
+        Ok(Self {
+            width: tiles.width() as i32,
+            height: tiles.height() as i32,
+            tiles,
+        })
     }
 
-    fn is_new_reflection(&mut self, beam: &Beam) -> bool {
-        if let Some(ref mut dirs) = self.energized_mirrors.get_mut(&beam.head) {
-            if dirs.contains(&beam.direction.opposite()) {
-                false
-            } else {
-                dirs.push(beam.direction.opposite());
-                true
-            }
-        } else {
-            self.energized_mirrors
-                .insert(beam.head, vec![beam.direction.opposite()]);
-            true
+    fn tile(&self, beam: &Beam) -> &Tile {
+        &self.tiles[(beam.head.x as usize, beam.head.y as usize)]
+    }
+
+    fn contains(&self, pos: Point) -> bool {
+        self.tiles.contains((pos.x as isize, pos.y as isize))
+    }
+
+    fn simulate_beam(&self, beam: Beam) -> usize {
+        count_energized(&self.simulate_beam_map(beam))
+    }
+
+    fn simulate_beam_map(&self, beam: Beam) -> Vec<Vec<bool>> {
+        self.simulate(beam).energized_tiles
+    }
+
+    #[allow(dead_code)]
+    fn simulate_beam_stats(&self, beam: Beam) -> BeamStats {
+        let state = self.simulate(beam);
+        BeamStats {
+            energized: count_energized(&state.energized_tiles),
+            splits: state.splits,
+            loops_cut: state.loops_cut,
         }
     }
 
-    fn simulate_beam(&mut self, beam: Beam) -> usize {
+    // Every simulation starts with a fresh state, so the grid can be shared freely
+    fn simulate(&self, beam: Beam) -> BeamState {
+        let mut state = BeamState::new(self);
         let mut beams_to_check: VecDeque<Beam> = VecDeque::from(vec![beam]);
         while let Some(beam) = beams_to_check.pop_front() {
-            match self.tile(&beam) {
-                '.' => {
-                    let next = beam.next(self, false);
-                    [next, None]
+            state.energize(&beam);
+            let outgoing = self.tile(&beam).outgoing(&beam.direction);
+            if outgoing.len() > 1 {
+                state.splits += 1;
+            }
+            for direction in outgoing {
+                if let Some(new_beam) = beam.clone().turn(direction).next(self, &mut state) {
+                    beams_to_check.push_back(new_beam);
                 }
-                '|' => match beam.direction.horizontal() {
-                    true => {
-                        let north = beam.clone().turn(North).next(self, true);
-                        let south = beam.turn(South).next(self, true);
-                        [north, south]
-                    }
-                    false => [beam.next(self, true), None],
-                },
-                '-' => match beam.direction.horizontal() {
-                    true => [beam.next(self, true), None],
-                    false => {
-                        let east = beam.clone().turn(East).next(self, true);
-                        let west = beam.turn(West).next(self, true);
-                        [east, west]
-                    }
-                },
-                '/' => {
-                    let reflected = match beam.direction {
-                        North => beam.turn(East),
-                        East => beam.turn(North),
-                        South => beam.turn(West),
-                        West => beam.turn(South),
+            }
+        }
+
+        state
+    }
+
+    // Beams entering the grid from every edge tile
+    fn edge_beams(&self) -> Vec<Beam> {
+        let left = repeat(0).zip(0..self.height).zip(repeat(East));
+        let right = repeat(self.width - 1).zip(0..self.height).zip(repeat(West));
+        let top = (0..self.width).zip(repeat(0)).zip(repeat(South));
+        let bottom = (0..self.width)
+            .zip(repeat(self.height - 1))
+            .zip(repeat(North));
+        left.chain(right)
+            .chain(top)
+            .chain(bottom)
+            .map(|((x, y), dir)| Beam::new(x, y, dir))
+            .collect()
+    }
+
+    #[allow(dead_code)]
+    fn max_energized(&self) -> usize {
+        self.edge_beams()
+            .into_iter()
+            .map(|beam| self.simulate_beam(beam))
+            .max()
+            .unwrap_or_default()
+    }
+
+    #[allow(dead_code)]
+    fn max_energized_par(&self) -> usize {
+        self.edge_beams()
+            .into_par_iter()
+            .map(|beam| self.simulate_beam(beam))
+            .max()
+            .unwrap_or_default()
+    }
+
+    // Much faster than max_energized_par, the starts share most of their paths
+    fn max_energized_memo(&self) -> usize {
+        let mut memo = EnergizedMemo::new(self);
+        self.edge_beams()
+            .into_iter()
+            .map(|beam| memo.energized_from(beam))
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+// A beam entering a non-empty tile in a direction
+type Node = (Point, Direction);
+
+// Tiles energized downstream of each node, reused between all of the edge starts.
+// Beams only change direction on non-empty tiles, so only those are memoized, the empty
+// tiles in between are added to the node the beam has left. Nodes in a loop energize the
+// same tiles, so they are grouped with Tarjan's SCC algorithm and share a single result.
+// Memory: 4 * (non-empty tiles) nodes at most, each with a bitset of width * height bits
+struct EnergizedMemo<'grid> {
+    grid: &'grid Grid,
+    indices: HashMap<Node, usize>,
+    lowlinks: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    // Tiles energized by a node itself and the nodes it leads to directly
+    own: Vec<Vec<u64>>,
+    successors: Vec<Vec<usize>>,
+    // Index into results, once the node's SCC is done
+    components: Vec<Option<usize>>,
+    results: Vec<Vec<u64>>,
+}
+
+impl<'grid> EnergizedMemo<'grid> {
+    fn new(grid: &'grid Grid) -> Self {
+        Self {
+            grid,
+            indices: HashMap::new(),
+            lowlinks: vec![],
+            on_stack: vec![],
+            stack: vec![],
+            own: vec![],
+            successors: vec![],
+            components: vec![],
+            results: vec![],
+        }
+    }
+
+    fn empty_bitset(&self) -> Vec<u64> {
+        vec![0; (self.grid.width * self.grid.height) as usize / 64 + 1]
+    }
+
+    fn set(&self, bits: &mut [u64], pos: Point) {
+        let idx = (pos.y * self.grid.width + pos.x) as usize;
+        bits[idx / 64] |= 1 << (idx % 64);
+    }
+
+    // Walks over the empty tiles from pos in the given direction, returning them along with
+    // the next node, unless the beam leaves the grid
+    fn segment(&self, mut pos: Point, dir: &Direction) -> (Vec<Point>, Option<Node>) {
+        let mut tiles = vec![];
+        let offset = dir.offset();
+        loop {
+            pos += offset;
+            if !self.grid.contains(pos) {
+                return (tiles, None);
+            }
+            tiles.push(pos);
+            let beam = Beam::new(pos.x, pos.y, dir.clone());
+            if !matches!(self.grid.tile(&beam), Tile::Empty) {
+                return (tiles, Some((pos, dir.clone())));
+            }
+        }
+    }
+
+    fn energized_from(&mut self, beam: Beam) -> usize {
+        let mut bits = self.empty_bitset();
+        let node = match self.grid.tile(&beam) {
+            Tile::Empty => {
+                self.set(&mut bits, beam.head);
+                let (tiles, node) = self.segment(beam.head, &beam.direction);
+                tiles.into_iter().for_each(|pos| self.set(&mut bits, pos));
+                node
+            }
+            _ => Some((beam.head, beam.direction)),
+        };
+
+        if let Some(node) = node {
+            let idx = match self.indices.get(&node) {
+                Some(idx) => *idx,
+                None => self.visit(node),
+            };
+            let component = self.components[idx].expect("Visited nodes should be finished");
+            bits.iter_mut()
+                .zip(&self.results[component])
+                .for_each(|(bits, result)| *bits |= result);
+        }
+        bits.iter().map(|bits| bits.count_ones() as usize).sum()
+    }
+
+    fn visit(&mut self, node: Node) -> usize {
+        let idx = self.lowlinks.len();
+        self.indices.insert(node.clone(), idx);
+        self.lowlinks.push(idx);
+        self.on_stack.push(true);
+        self.stack.push(idx);
+        self.own.push(vec![]);
+        self.successors.push(vec![]);
+        self.components.push(None);
+
+        let (pos, dir) = node;
+        let mut own = self.empty_bitset();
+        self.set(&mut own, pos);
+        let beam = Beam::new(pos.x, pos.y, dir.clone());
+        for out in self.grid.tile(&beam).outgoing(&dir) {
+            let (tiles, next) = self.segment(pos, &out);
+            tiles.into_iter().for_each(|pos| self.set(&mut own, pos));
+            let Some(next) = next else {
+                continue;
+            };
+            let next_idx = match self.indices.get(&next) {
+                Some(next_idx) => {
+                    if self.on_stack[*next_idx] {
+                        self.lowlinks[idx] = self.lowlinks[idx].min(*next_idx);
                     }
-                    .next(self, true);
-                    [reflected, None]
+                    *next_idx
+                }
+                None => {
+                    let next_idx = self.visit(next);
+                    self.lowlinks[idx] = self.lowlinks[idx].min(self.lowlinks[next_idx]);
+                    next_idx
+                }
+            };
+            self.successors[idx].push(next_idx);
+        }
+        self.own[idx] = own;
+
+        // idx is the root of an SCC, everything above it on the stack belongs to the SCC
+        if self.lowlinks[idx] == idx {
+            let mut result = self.empty_bitset();
+            let mut members = vec![];
+            while let Some(member) = self.stack.pop() {
+                self.on_stack[member] = false;
+                members.push(member);
+                if member == idx {
+                    break;
                 }
-                '\\' => {
-                    let reflected = match beam.direction {
-                        North => beam.turn(West),
-                        East => beam.turn(South),
-                        South => beam.turn(East),
-                        West => beam.turn(North),
+            }
+            for member in members.iter() {
+                let own = std::mem::take(&mut self.own[*member]);
+                result
+                    .iter_mut()
+                    .zip(&own)
+                    .for_each(|(bits, own)| *bits |= own);
+                // Successors in the same SCC are not finished yet, their tiles are added as members
+                for successor in self.successors[*member].iter() {
+                    if let Some(component) = self.components[*successor] {
+                        result
+                            .iter_mut()
+                            .zip(&self.results[component])
+                            .for_each(|(bits, other)| *bits |= other);
                     }
-                    .next(self, true);
-                    [reflected, None]
                 }
-                _ => [None, None],
             }
-            .into_iter()
-            .flatten()
-            .for_each(|new_beam| beams_to_check.push_back(new_beam));
+            let component = self.results.len();
+            members
+                .iter()
+                .for_each(|member| self.components[*member] = Some(component));
+            self.results.push(result);
         }
-
-        let result = self
-            .energized_tiles
-            .iter()
-            .map(|row| row.iter().filter(|tile| **tile).count())
-            .sum();
-        self.reset();
-        result
+        idx
     }
 }
 
+fn count_energized(map: &[Vec<bool>]) -> usize {
+    map.iter()
+        .map(|row| row.iter().filter(|tile| **tile).count())
+        .sum()
+}
+
+// Energized tiles as '#', same as in the puzzle's worked example
+#[allow(dead_code)]
+fn render_energized(map: &[Vec<bool>]) -> String {
+    map.iter()
+        .map(|row| {
+            row.iter()
+                .map(|energized| if *energized { '#' } else { '.' })
+                .collect::<String>()
+        })
+        .join("\n")
+}
+
 impl AocTask for Day16 {
     fn directory(&self) -> PathBuf {
         "tasks/day_16".into()
     }
 
     fn solution(&self, input: AocStringIter, phase: usize) -> Result<AocSolution, BoxedError> {
-        let mut tiles = Tiles::new(input);
+        let grid = Grid::new(input)?;
 
         match phase {
-            1 => tiles.simulate_beam(Beam::new(0, 0, East)),
-            2 => {
-                let left = repeat(0).zip(0..tiles.height).zip(repeat(East));
-                let right = repeat(tiles.width - 1)
-                    .zip(0..tiles.height)
-                    .zip(repeat(West));
-                let top = (0..tiles.width).zip(repeat(0)).zip(repeat(South));
-                let bottom = (0..tiles.width)
-                    .zip(repeat(tiles.height - 1))
-                    .zip(repeat(North));
-                let beams = left.chain(right).chain(top).chain(bottom);
-                beams
-                    .map(|((x, y), dir)| tiles.simulate_beam(Beam::new(x, y, dir)))
-                    .max()
-                    .unwrap_or_default()
-            }
+            1 => grid.simulate_beam(Beam::new(0, 0, East)),
+            2 => grid.max_energized_memo(),
             _ => unimplemented!(),
         }
         .solved()
     }
 }
+
+#[cfg(test)]
+mod test {
+    extern crate test;
+
+    use test::Bencher;
+
+    use super::{render_energized, Beam, BeamStats, Direction::*, EnergizedMemo, Grid};
+
+    const SAMPLE: &str = r".|...\....
+|.-.\.....
+.....|-...
+........|.
+..........
+.........\
+..../.\\..
+.-.-/..|..
+.|....-|.\
+..//.|....";
+
+    fn grid(input: &str) -> Grid {
+        Grid::new(Box::new(input.lines().map(String::from))).unwrap()
+    }
+
+    #[test]
+    fn parallel_edge_scan() {
+        let grid = grid(SAMPLE);
+        assert_eq!(grid.simulate_beam(Beam::new(0, 0, East)), 46);
+        assert_eq!(grid.max_energized_par(), 51);
+        assert_eq!(grid.max_energized(), 51);
+    }
+
+    #[test]
+    fn independent_simulations() {
+        let grid = grid(SAMPLE);
+        let first = grid.simulate_beam(Beam::new(3, 0, South));
+        let second = grid.simulate_beam(Beam::new(0, 0, East));
+        assert_eq!(first, 51);
+        assert_eq!(second, 46);
+        assert_eq!(grid.simulate_beam(Beam::new(3, 0, South)), first);
+    }
+
+    #[test]
+    fn energized_map() {
+        let map = grid(SAMPLE).simulate_beam_map(Beam::new(0, 0, East));
+        assert_eq!(map.iter().flatten().filter(|tile| **tile).count(), 46);
+        assert_eq!(
+            render_energized(&map),
+            "######....
+.#...#....
+.#...#####
+.#...##...
+.#...##...
+.#...##...
+.#..####..
+########..
+.#######..
+.#...#.#.."
+        );
+    }
+
+    // An empty grid with mirrors in the corners, sending the beam around the edges forever
+    fn open_grid(size: usize) -> Grid {
+        let edge = move |left: char, right: char| format!("{left}{}{right}", ".".repeat(size - 2));
+        let rows = (0..size).map(move |y| match y {
+            0 => edge('/', '\\'),
+            y if y == size - 1 => edge('\\', '/'),
+            _ => ".".repeat(size),
+        });
+        Grid::new(Box::new(rows)).unwrap()
+    }
+
+    #[test]
+    fn open_grid_loop() {
+        assert_eq!(open_grid(10).simulate_beam(Beam::new(1, 0, East)), 36);
+    }
+
+    #[bench]
+    fn open_grid_500x500(b: &mut Bencher) {
+        let grid = open_grid(500);
+        b.iter(|| grid.simulate_beam(Beam::new(1, 0, East)));
+    }
+
+    #[test]
+    fn cross_splitter() {
+        let splitter = grid(
+            "...
+.+.
+...",
+        );
+        assert_eq!(
+            render_energized(&splitter.simulate_beam_map(Beam::new(0, 1, East))),
+            ".#.
+###
+.#."
+        );
+
+        // The absorber is lit, but nothing gets past it
+        let absorbing = grid("..o..");
+        assert_eq!(absorbing.simulate_beam(Beam::new(0, 0, East)), 3);
+    }
+
+    #[test]
+    fn unknown_tile() {
+        let error = Grid::new(Box::new(["..x..".to_string()].into_iter()))
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("Unexpected tile: x"));
+    }
+
+    #[test]
+    fn memoized_edge_scan() {
+        let grid = grid(SAMPLE);
+        assert_eq!(grid.max_energized_memo(), 51);
+
+        let mut memo = EnergizedMemo::new(&grid);
+        for beam in grid.edge_beams() {
+            assert_eq!(memo.energized_from(beam.clone()), grid.simulate_beam(beam));
+        }
+    }
+
+    #[test]
+    fn mirror_reflections() {
+        for (incoming, slash, backslash) in [
+            (North, East, West),
+            (East, North, South),
+            (South, West, East),
+            (West, South, North),
+        ] {
+            assert_eq!(incoming.reflect_slash(), slash);
+            assert_eq!(incoming.reflect_backslash(), backslash);
+            // Reflecting twice off the same mirror goes back the original way
+            assert_eq!(slash.reflect_slash(), incoming);
+            assert_eq!(backslash.reflect_backslash(), incoming);
+        }
+    }
+
+    #[test]
+    fn sample_beam_stats() {
+        let grid = grid(SAMPLE);
+        assert_eq!(
+            grid.simulate_beam_stats(Beam::new(0, 0, East)),
+            BeamStats {
+                energized: 46,
+                splits: 9,
+                loops_cut: 3,
+            }
+        );
+        assert_eq!(
+            grid.simulate_beam_stats(Beam::new(3, 0, South)),
+            BeamStats {
+                energized: 51,
+                splits: 9,
+                loops_cut: 4,
+            }
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use std::ops::{Add, AddAssign, Sub};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Point {
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+}
+
+impl Add for Point {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl AddAssign for Point {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Point {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+// Grid directions, with y growing towards the South
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+use Direction::*;
+
+#[allow(dead_code)]
+impl Direction {
+    pub fn horizontal(&self) -> bool {
+        matches!(self, East | West)
+    }
+
+    pub fn offset(&self) -> Point {
+        match self {
+            North => Point::new(0, -1),
+            East => Point::new(1, 0),
+            South => Point::new(0, 1),
+            West => Point::new(-1, 0),
+        }
+    }
+
+    pub fn opposite(&self) -> Self {
+        match self {
+            North => South,
+            East => West,
+            South => North,
+            West => East,
+        }
+    }
+
+    pub fn turn_left(&self) -> Self {
+        match self {
+            North => West,
+            East => North,
+            South => East,
+            West => South,
+        }
+    }
+
+    pub fn turn_right(&self) -> Self {
+        match self {
+            North => East,
+            East => South,
+            South => West,
+            West => North,
+        }
+    }
+
+    // Direction after hitting a '/' mirror
+    pub fn reflect_slash(&self) -> Self {
+        match self.horizontal() {
+            true => self.turn_left(),
+            false => self.turn_right(),
+        }
+    }
+
+    // Direction after hitting a '\' mirror
+    pub fn reflect_backslash(&self) -> Self {
+        match self.horizontal() {
+            true => self.turn_right(),
+            false => self.turn_left(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Direction::*, Point};
+
+    #[test]
+    fn point_arithmetic() {
+        let a = Point::new(3, -2);
+        let b = Point::new(-1, 5);
+        assert_eq!(a + b, Point::new(2, 3));
+        assert_eq!(a - b, Point::new(4, -7));
+        assert_eq!(a + b - b, a);
+        let mut c = a;
+        c += b;
+        assert_eq!(c, a + b);
+        // Points are ordered by x first
+        assert!(Point::new(0, 9) < Point::new(1, 0));
+    }
+
+    #[test]
+    fn turns() {
+        for dir in [North, East, South, West] {
+            assert_eq!(dir.turn_left().turn_right(), dir);
+            assert_eq!(dir.turn_right().turn_right(), dir.opposite());
+            assert_eq!(dir.turn_left().turn_left(), dir.opposite());
+            assert_eq!(dir.offset() + dir.opposite().offset(), Point::default());
+        }
+        assert_eq!(North.turn_right(), East);
+        assert_eq!(North.turn_left(), West);
+        assert_eq!(Point::new(1, 1) + South.offset(), Point::new(1, 2));
+    }
+}
@@ -0,0 +1,162 @@
+use std::ops::{Index, IndexMut};
+
+use aoc_framework::AocStringIter;
+
+// Row-major 2D grid, indexed by (x, y)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid<T> {
+    data: Vec<T>,
+    width: usize,
+    height: usize,
+}
+
+impl Grid<char> {
+    pub fn from_chars(input: AocStringIter) -> Self {
+        let mut data = vec![];
+        let mut width = None;
+        let mut height = 0;
+        for line in input {
+            let len = data.len();
+            data.extend(line.chars());
+            let line_width = data.len() - len;
+            assert_eq!(
+                *width.get_or_insert(line_width),
+                line_width,
+                "Grid rows should have the same width, row {height} differs"
+            );
+            height += 1;
+        }
+        Self {
+            data,
+            width: width.unwrap_or_default(),
+            height,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl<T> Grid<T> {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Grid<U> {
+        Grid {
+            data: self.data.into_iter().map(f).collect(),
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    // Like map, stopping at the first error
+    pub fn try_map<U, E>(self, f: impl FnMut(T) -> Result<U, E>) -> Result<Grid<U>, E> {
+        Ok(Grid {
+            data: self.data.into_iter().map(f).collect::<Result<_, _>>()?,
+            width: self.width,
+            height: self.height,
+        })
+    }
+
+    pub fn contains(&self, (x, y): (isize, isize)) -> bool {
+        x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height
+    }
+
+    pub fn get(&self, (x, y): (usize, usize)) -> Option<&T> {
+        (x < self.width && y < self.height).then(|| &self.data[y * self.width + x])
+    }
+
+    // Positions of the orthogonal neighbors inside the grid, in N, E, S, W order
+    pub fn neighbors(&self, (x, y): (usize, usize)) -> impl Iterator<Item = (usize, usize)> + '_ {
+        [(0, -1), (1, 0), (0, 1), (-1, 0)]
+            .into_iter()
+            .map(move |(dx, dy)| (x as isize + dx, y as isize + dy))
+            .filter(|pos| self.contains(*pos))
+            .map(|(x, y)| (x as usize, y as usize))
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        self.data.chunks(self.width.max(1))
+    }
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, (x, y): (usize, usize)) -> &Self::Output {
+        self.get((x, y))
+            .unwrap_or_else(|| panic!("({x}, {y}) should be inside the grid"))
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut Self::Output {
+        assert!(
+            x < self.width && y < self.height,
+            "({x}, {y}) should be inside the grid"
+        );
+        &mut self.data[y * self.width + x]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Grid;
+
+    fn grid(input: &'static str) -> Grid<char> {
+        Grid::from_chars(Box::new(input.lines().map(String::from)))
+    }
+
+    #[test]
+    fn indexing_and_bounds() {
+        let grid = grid("abc\ndef");
+        assert_eq!((grid.width(), grid.height()), (3, 2));
+        assert_eq!(grid[(2, 0)], 'c');
+        assert_eq!(grid[(0, 1)], 'd');
+        assert_eq!(grid.get((3, 0)), None);
+        assert_eq!(grid.get((0, 2)), None);
+        assert!(grid.contains((2, 1)));
+        assert!(!grid.contains((-1, 0)));
+        assert!(!grid.contains((3, 1)));
+        assert_eq!(
+            grid.rows().collect::<Vec<_>>(),
+            vec![&['a', 'b', 'c'][..], &['d', 'e', 'f'][..]]
+        );
+    }
+
+    #[test]
+    fn neighbors() {
+        let grid = grid("abc\ndef\nghi");
+        assert_eq!(
+            grid.neighbors((0, 0)).collect::<Vec<_>>(),
+            vec![(1, 0), (0, 1)]
+        );
+        assert_eq!(
+            grid.neighbors((1, 1)).collect::<Vec<_>>(),
+            vec![(1, 0), (2, 1), (1, 2), (0, 1)]
+        );
+        assert_eq!(
+            grid.neighbors((2, 2)).collect::<Vec<_>>(),
+            vec![(2, 1), (1, 2)]
+        );
+    }
+
+    #[test]
+    fn try_map() {
+        let digits = grid("12\n34").try_map(|chr| chr.to_digit(10).ok_or(chr));
+        assert_eq!(digits.map(|grid| grid[(1, 1)]), Ok(4));
+        assert_eq!(
+            grid("1x").try_map(|chr| chr.to_digit(10).ok_or(chr)),
+            Err('x')
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "same width")]
+    fn ragged_rows() {
+        grid("abc\nde");
+    }
+}
@@ -0,0 +1,89 @@
+use std::{fs, iter::from_fn};
+
+use crate::{task_for, BoxedError};
+use aoc_framework::AocStringIter;
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct InputStats {
+    pub lines: usize,
+    pub max_line_len: usize,
+    // Line endings are not counted
+    pub total_chars: usize,
+}
+
+// Groups of lines separated by blank lines, runs of blank lines don't produce empty groups
+pub fn blank_separated_groups<'a>(
+    mut input: AocStringIter<'a>,
+) -> impl Iterator<Item = Vec<String>> + 'a {
+    from_fn(move || {
+        let group: Vec<_> = input
+            .by_ref()
+            .skip_while(|line| line.is_empty())
+            .take_while(|line| !line.is_empty())
+            .collect();
+        (!group.is_empty()).then_some(group)
+    })
+}
+
+pub fn input_stats(input: AocStringIter) -> InputStats {
+    input.fold(InputStats::default(), |stats, line| {
+        let len = line.chars().count();
+        InputStats {
+            lines: stats.lines + 1,
+            max_line_len: stats.max_line_len.max(len),
+            total_chars: stats.total_chars + len,
+        }
+    })
+}
+
+// Stats of the day's real input
+#[allow(dead_code)]
+pub fn stats_for_day(day: usize) -> Result<InputStats, BoxedError> {
+    let task = task_for(day).ok_or_else(|| format!("Day {day} is not implemented"))?;
+    let input = fs::read_to_string(task.directory().join("in"))?;
+    let lines: Vec<String> = input.lines().map(String::from).collect();
+    Ok(input_stats(Box::new(lines.into_iter())))
+}
+
+// Extra example checked in next to the day's input, for tests which need more than the
+// example_in used by the framework
+#[cfg(test)]
+pub fn sample(day: usize) -> AocStringIter<'static> {
+    let path = format!("tasks/day_{day:02}/sample_in");
+    let sample =
+        fs::read_to_string(&path).unwrap_or_else(|err| panic!("{path} should be readable: {err}"));
+    Box::new(
+        sample
+            .lines()
+            .map(String::from)
+            .collect::<Vec<_>>()
+            .into_iter(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{blank_separated_groups, input_stats, stats_for_day, InputStats};
+
+    #[test]
+    fn three_groups() {
+        let input = "\na\nb\n\nc\n\n\n\nd\ne\nf\n\n";
+        let groups: Vec<_> =
+            blank_separated_groups(Box::new(input.lines().map(String::from))).collect();
+        assert_eq!(groups, vec![vec!["a", "b"], vec!["c"], vec!["d", "e", "f"]]);
+    }
+
+    #[test]
+    fn small_input_stats() {
+        let input = "abc\n\nabcdé";
+        assert_eq!(
+            input_stats(Box::new(input.lines().map(String::from))),
+            InputStats {
+                lines: 3,
+                max_line_len: 5,
+                total_chars: 8,
+            }
+        );
+        assert_eq!(stats_for_day(6).unwrap().lines, 2);
+    }
+}
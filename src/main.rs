@@ -1,7 +1,10 @@
 #![feature(lint_reasons)]
 #![feature(result_flattening)]
+#![cfg_attr(test, feature(test))]
 #![expect(unused_variables)]
 
+mod bench;
+mod context;
 mod day_01;
 mod day_02;
 mod day_03;
@@ -27,6 +30,14 @@ mod day_22;
 mod day_23;
 mod day_24;
 mod day_25;
+mod geometry;
+mod grid;
+mod input;
+mod math;
+mod runner;
+mod task_ext;
+#[cfg(test)]
+mod verify;
 
 use day_01::Day01;
 use day_02::Day02;
@@ -59,10 +70,9 @@ use std::error::Error;
 use aoc_framework::{check_solved_tasks, BoxedAocTask};
 type BoxedError = Box<dyn Error + Send + Sync>;
 
-fn main() -> color_eyre::Result<()> {
-    color_eyre::install()?;
-
-    let tasks: Vec<BoxedAocTask> = vec![
+// One task per day, in order
+pub fn all_tasks() -> Vec<BoxedAocTask> {
+    vec![
         Box::new(Day01),
         Box::new(Day02),
         Box::new(Day03),
@@ -73,9 +83,9 @@ fn main() -> color_eyre::Result<()> {
         Box::new(Day08),
         Box::new(Day09),
         Box::new(Day10),
-        Box::new(Day11),
-        Box::new(Day12),
-        Box::new(Day13),
+        Box::new(Day11::default()),
+        Box::new(Day12::default()),
+        Box::new(Day13::default()),
         Box::new(Day14),
         Box::new(Day15),
         Box::new(Day16),
@@ -88,9 +98,37 @@ fn main() -> color_eyre::Result<()> {
         Box::new(Day23),
         Box::new(Day24),
         Box::new(Day25),
-    ];
+    ]
+}
+
+pub fn task_for(day: usize) -> Option<BoxedAocTask> {
+    all_tasks().into_iter().nth(day.checked_sub(1)?)
+}
+
+fn main() -> color_eyre::Result<()> {
+    color_eyre::install()?;
+
+    let tasks = all_tasks();
 
     check_solved_tasks(tasks, 2)?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{all_tasks, task_for};
+
+    #[test]
+    fn registry() {
+        assert_eq!(all_tasks().len(), 25);
+        assert_eq!(
+            task_for(12).map(|task| task.directory()),
+            Some(PathBuf::from("tasks/day_12"))
+        );
+        assert!(task_for(0).is_none());
+        assert!(task_for(26).is_none());
+    }
+}
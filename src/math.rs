@@ -0,0 +1,106 @@
+use crate::BoxedError;
+
+pub fn gcd(a: usize, b: usize) -> usize {
+    match b {
+        0 => a,
+        _ => gcd(b, a % b),
+    }
+}
+
+pub fn lcm(a: usize, b: usize) -> usize {
+    match (a, b) {
+        (0, _) | (_, 0) => 0,
+        _ => a / gcd(a, b) * b,
+    }
+}
+
+// The LCM of no numbers is 1
+pub fn lcm_many(numbers: impl IntoIterator<Item = usize>) -> usize {
+    numbers.into_iter().fold(1, lcm)
+}
+
+pub trait CheckedArithmetic: Sized {
+    const ZERO: Self;
+    const ONE: Self;
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+}
+
+macro_rules! impl_checked_arithmetic {
+    ($($t:ty),*) => {$(
+        impl CheckedArithmetic for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_add(self, rhs)
+            }
+
+            fn checked_mul(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_mul(self, rhs)
+            }
+        }
+    )*};
+}
+
+impl_checked_arithmetic!(u32, u64, usize, i32, i64, i128);
+
+// Sum which errors instead of wrapping around in release builds
+#[allow(dead_code)]
+pub fn checked_sum<T: CheckedArithmetic>(
+    numbers: impl IntoIterator<Item = T>,
+) -> Result<T, BoxedError> {
+    numbers
+        .into_iter()
+        .try_fold(T::ZERO, T::checked_add)
+        .ok_or_else(|| "Sum overflowed".into())
+}
+
+// Product which errors instead of wrapping around in release builds
+pub fn checked_product<T: CheckedArithmetic>(
+    numbers: impl IntoIterator<Item = T>,
+) -> Result<T, BoxedError> {
+    numbers
+        .into_iter()
+        .try_fold(T::ONE, T::checked_mul)
+        .ok_or_else(|| "Product overflowed".into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{checked_product, checked_sum, gcd, lcm, lcm_many};
+
+    #[test]
+    fn gcd_values() {
+        assert_eq!(gcd(48, 36), 12);
+        assert_eq!(gcd(36, 48), 12);
+        assert_eq!(gcd(17, 5), 1);
+        assert_eq!(gcd(0, 7), 7);
+        assert_eq!(gcd(7, 0), 7);
+    }
+
+    #[test]
+    fn lcm_values() {
+        assert_eq!(lcm(4, 6), 12);
+        assert_eq!(lcm(0, 6), 0);
+        assert_eq!(lcm_many([8, 9, 21]), 504);
+        assert_eq!(lcm_many([12, 18, 24]), 72);
+        assert_eq!(lcm_many([4, 8, 2]), 8);
+        assert_eq!(lcm_many([]), 1);
+    }
+
+    #[test]
+    fn checked_folds() {
+        assert_eq!(checked_sum([1u32, 2, 3]).ok(), Some(6));
+        assert_eq!(checked_product([2i64, -3, 4]).ok(), Some(-24));
+        assert_eq!(checked_product(Vec::<usize>::new()).ok(), Some(1));
+        assert_eq!(
+            checked_sum([usize::MAX, 1]).map_err(|err| err.to_string()),
+            Err("Sum overflowed".to_string())
+        );
+        assert_eq!(
+            checked_product([i64::MAX, 2]).map_err(|err| err.to_string()),
+            Err("Product overflowed".to_string())
+        );
+    }
+}
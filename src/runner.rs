@@ -0,0 +1,285 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs,
+};
+
+use crate::{all_tasks, task_for, BoxedError};
+use aoc_framework::{AocSolution, AocTask, BoxedAocTask};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const PHASES: [usize; 2] = [1, 2];
+
+// Line count of the task's real input
+#[allow(dead_code)]
+pub fn check_input(task: &dyn AocTask) -> Result<usize, BoxedError> {
+    let directory = task.directory();
+    if !directory.is_dir() {
+        return Err(format!("Input directory {} does not exist", directory.display()).into());
+    }
+    let path = directory.join("in");
+    if !path.is_file() {
+        return Err(format!("Input file {} does not exist", path.display()).into());
+    }
+    match fs::read_to_string(&path)?.lines().count() {
+        0 => Err(format!("Input file {} is empty", path.display()).into()),
+        lines => Ok(lines),
+    }
+}
+
+// The day a task solves, read from its tasks/day_NN directory
+fn task_day(task: &dyn AocTask) -> Option<usize> {
+    task.directory()
+        .file_name()?
+        .to_str()?
+        .strip_prefix("day_")?
+        .parse()
+        .ok()
+}
+
+// Runs a phase of a task on its real input
+fn run_task(task: &dyn AocTask, phase: usize) -> Result<AocSolution, BoxedError> {
+    let input = fs::read_to_string(task.directory().join("in"))?;
+    let lines: Vec<String> = input.lines().map(String::from).collect();
+    task.solution(Box::new(lines.into_iter()), phase)
+}
+
+fn run_day(day: usize, phase: usize) -> Result<AocSolution, BoxedError> {
+    let task = task_for(day).ok_or_else(|| format!("Day {day} is not implemented"))?;
+    run_task(task.as_ref(), phase)
+}
+
+// Results of both phases of every implemented day, ordered by day and phase. Days without
+// a real input are unsolved stubs, so they're skipped
+#[allow(dead_code)]
+pub fn run_all_parallel() -> Vec<(usize, Result<AocSolution, BoxedError>)> {
+    let days: Vec<_> = all_tasks()
+        .iter()
+        .zip(1..)
+        .filter(|(task, _)| check_input(task.as_ref()).is_ok())
+        .map(|(_, day)| day)
+        .collect();
+    run_days_parallel(&days)
+}
+
+// Every job creates its own task, so the tasks themselves don't have to be shared between threads
+pub fn run_days_parallel(days: &[usize]) -> Vec<(usize, Result<AocSolution, BoxedError>)> {
+    days.par_iter()
+        .flat_map(|day| {
+            PHASES
+                .par_iter()
+                .map(move |phase| (*day, run_day(*day, *phase)))
+        })
+        .collect()
+}
+
+#[allow(dead_code)]
+pub fn run_days(days: &[usize]) -> Vec<(usize, Result<AocSolution, BoxedError>)> {
+    days.iter()
+        .flat_map(|day| {
+            PHASES
+                .iter()
+                .map(move |phase| (*day, run_day(*day, *phase)))
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct SolutionRecord {
+    day: usize,
+    phase: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    answer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+// Both phases of every task with an input, the inputs are keyed by the day of the task
+#[allow(dead_code)]
+pub fn solutions_json(tasks: &[BoxedAocTask], inputs: &HashMap<usize, String>) -> String {
+    let records: Vec<_> = tasks
+        .iter()
+        .filter_map(|task| {
+            let day = task_day(task.as_ref())?;
+            Some((task, day, inputs.get(&day)?))
+        })
+        .flat_map(|(task, day, input)| {
+            PHASES.iter().map(move |phase| {
+                let lines: Vec<String> = input.lines().map(String::from).collect();
+                let (answer, error) = match task.solution(Box::new(lines.into_iter()), *phase) {
+                    Ok(answer) => (Some(answer.to_string()), None),
+                    Err(err) => (None, Some(err.to_string())),
+                };
+                SolutionRecord {
+                    day,
+                    phase: *phase,
+                    answer,
+                    error,
+                }
+            })
+        })
+        .collect();
+    serde_json::to_string(&records).expect("Solution records should serialize")
+}
+
+// Answers of both phases keyed by (day, phase), failed phases are kept as their error.
+// Tasks outside of a tasks/day_NN directory have no day to key them by, so they're skipped
+#[allow(dead_code)]
+pub fn snapshot_solutions(tasks: &[BoxedAocTask]) -> BTreeMap<(usize, usize), String> {
+    tasks
+        .iter()
+        .filter_map(|task| Some((task, task_day(task.as_ref())?)))
+        .flat_map(|(task, day)| {
+            PHASES.iter().map(move |phase| {
+                let answer = match run_task(task.as_ref(), *phase) {
+                    Ok(answer) => answer.to_string(),
+                    Err(err) => format!("Error: {err}"),
+                };
+                ((day, *phase), answer)
+            })
+        })
+        .collect()
+}
+
+// (day, phase, before, after) for every answer that differs, answers missing from one of the
+// snapshots are empty
+#[allow(dead_code)]
+pub fn diff_snapshots(
+    before: &BTreeMap<(usize, usize), String>,
+    after: &BTreeMap<(usize, usize), String>,
+) -> Vec<(usize, usize, String, String)> {
+    let keys: BTreeSet<_> = before.keys().chain(after.keys()).collect();
+    keys.into_iter()
+        .filter_map(|key| {
+            let (old, new) = (before.get(key), after.get(key));
+            (old != new).then(|| {
+                (
+                    key.0,
+                    key.1,
+                    old.cloned().unwrap_or_default(),
+                    new.cloned().unwrap_or_default(),
+                )
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashMap, path::PathBuf};
+
+    use super::{
+        check_input, diff_snapshots, run_all_parallel, run_days, run_days_parallel,
+        snapshot_solutions, solutions_json, SolutionRecord,
+    };
+    use crate::{day_01::Day01, day_09::Day09, BoxedError};
+    use aoc_framework::{traits::*, AocSolution, AocStringIter, AocTask, BoxedAocTask};
+
+    struct MissingInput;
+
+    impl AocTask for MissingInput {
+        fn directory(&self) -> PathBuf {
+            "tasks/day_missing".into()
+        }
+
+        fn solution(&self, input: AocStringIter, _: usize) -> Result<AocSolution, BoxedError> {
+            input.solved()
+        }
+    }
+
+    fn answers(results: Vec<(usize, Result<AocSolution, BoxedError>)>) -> Vec<(usize, String)> {
+        results
+            .into_iter()
+            .map(|(day, result)| (day, result.unwrap().to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        let days = [1, 4, 15, 16];
+        assert_eq!(answers(run_days_parallel(&days)), answers(run_days(&days)));
+    }
+
+    #[test]
+    fn run_all_skips_stubs() {
+        let results = run_all_parallel();
+        assert_eq!(results.len(), 16 * 2);
+        assert!(results
+            .iter()
+            .all(|(day, result)| *day <= 16 && result.is_ok()));
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let tasks: Vec<BoxedAocTask> = vec![Box::new(Day01)];
+        let inputs =
+            HashMap::from([(1, "1abc2\npqr3stu8vwx\na1b2c3d4e5f\ntreb7uchet".to_string())]);
+        let json = solutions_json(&tasks, &inputs);
+        let records: Vec<SolutionRecord> = serde_json::from_str(&json).unwrap();
+        let record = |phase| SolutionRecord {
+            day: 1,
+            phase,
+            answer: Some("142".to_string()),
+            error: None,
+        };
+        assert_eq!(records, vec![record(1), record(2)]);
+        assert!(json.starts_with(r#"[{"day":1,"phase":1,"answer":"142"}"#));
+    }
+
+    #[test]
+    fn json_day_from_task() {
+        let tasks: Vec<BoxedAocTask> = vec![Box::new(Day09)];
+        let inputs = HashMap::from([
+            (1, "1abc2".to_string()),
+            (9, "0 3 6 9 12 15\n1 3 6 10 15 21".to_string()),
+        ]);
+        let records: Vec<SolutionRecord> =
+            serde_json::from_str(&solutions_json(&tasks, &inputs)).unwrap();
+        let record = |phase, answer: &str| SolutionRecord {
+            day: 9,
+            phase,
+            answer: Some(answer.to_string()),
+            error: None,
+        };
+        assert_eq!(records, vec![record(1, "46"), record(2, "-3")]);
+    }
+
+    #[test]
+    fn input_presence() {
+        assert_eq!(
+            check_input(&MissingInput).map_err(|err| err.to_string()),
+            Err("Input directory tasks/day_missing does not exist".to_string())
+        );
+        assert!(check_input(&Day01).unwrap() > 0);
+    }
+
+    #[test]
+    fn snapshot_day_from_task() {
+        let tasks: Vec<BoxedAocTask> = vec![Box::new(Day09), Box::new(MissingInput)];
+        let snapshot = snapshot_solutions(&tasks);
+        assert_eq!(
+            snapshot.keys().copied().collect::<Vec<_>>(),
+            vec![(9, 1), (9, 2)]
+        );
+        assert_eq!(snapshot[&(9, 1)], "1887980197");
+    }
+
+    #[test]
+    fn snapshot_diff() {
+        let tasks: Vec<BoxedAocTask> = vec![Box::new(Day01)];
+        let snapshot = snapshot_solutions(&tasks);
+        assert_eq!(snapshot.len(), 2);
+        assert!(diff_snapshots(&snapshot, &snapshot).is_empty());
+
+        let mut changed = snapshot.clone();
+        changed.insert((1, 2), "0".to_string());
+        changed.remove(&(1, 1));
+        assert_eq!(
+            diff_snapshots(&snapshot, &changed),
+            vec![
+                (1, 1, snapshot[&(1, 1)].clone(), String::new()),
+                (1, 2, snapshot[&(1, 2)].clone(), "0".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use crate::BoxedError;
+use aoc_framework::{AocSolution, AocStringIter, AocTask};
+
+#[allow(dead_code)]
+pub trait AocTaskExt: AocTask {
+    // Runs both phases on the same input
+    fn both_phases(&self, input: AocStringIter) -> Result<(AocSolution, AocSolution), BoxedError> {
+        let lines: Vec<String> = input.collect();
+        self.solve_both(|| Box::new(lines.clone().into_iter()))
+    }
+
+    // AocStringIter can only be consumed once, so the input is passed as a source of fresh
+    // iterators instead, e.g. `|| Box::new(input.lines().map(String::from))`. This avoids
+    // buffering the input when it can be read again cheaply
+    fn solve_both<'a>(
+        &self,
+        input: impl Fn() -> AocStringIter<'a>,
+    ) -> Result<(AocSolution, AocSolution), BoxedError> {
+        Ok((self.solution(input(), 1)?, self.solution(input(), 2)?))
+    }
+}
+
+impl<T: AocTask + ?Sized> AocTaskExt for T {}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::AocTaskExt;
+    use crate::{day_01::Day01, day_06::Day06};
+
+    fn input(input: &'static str) -> aoc_framework::AocStringIter<'static> {
+        Box::new(input.lines().map(String::from))
+    }
+
+    #[test]
+    fn day_01_both_phases() {
+        let (first, second) = Day01
+            .both_phases(input("two1nine\n4nineeightseven2\nzoneight234"))
+            .unwrap();
+        assert_eq!(first.to_string(), "77");
+        assert_eq!(second.to_string(), "85");
+        // Phase 1 only accepts lines with numeric digits
+        assert!(Day01.both_phases(input("two1nine\neightwothree")).is_err());
+    }
+
+    #[test]
+    fn day_06_solve_both() {
+        let input = fs::read_to_string("tasks/day_06/example_in").unwrap();
+        let (first, second) = Day06
+            .solve_both(|| Box::new(input.lines().map(String::from)))
+            .unwrap();
+        assert_eq!(first.to_string(), "288");
+        assert_eq!(second.to_string(), "71503");
+    }
+}
@@ -0,0 +1,63 @@
+use std::{collections::BTreeMap, fs};
+
+use crate::task_for;
+
+// Runs the given phase on the day's real input and compares the answer
+pub fn verify_day(day: usize, phase: usize, expected: &str) {
+    let task = task_for(day).unwrap_or_else(|| panic!("Day {day} should be implemented"));
+    let path = task.directory().join("in");
+    let input = fs::read_to_string(&path).unwrap_or_else(|err| {
+        panic!(
+            "Day {day} input {} should be readable: {err}",
+            path.display()
+        )
+    });
+    let lines: Vec<String> = input.lines().map(String::from).collect();
+    let answer = task
+        .solution(Box::new(lines.into_iter()), phase)
+        .unwrap_or_else(|err| panic!("Day {day} phase {phase} failed: {err}"));
+    assert_eq!(
+        answer.to_string(),
+        expected,
+        "Day {day} phase {phase} has a wrong answer"
+    );
+}
+
+// Parses the `"day.phase" = "answer"` entries of the answers file
+fn parse_answers(input: &str) -> Vec<(usize, usize, String)> {
+    let answers: BTreeMap<String, String> =
+        toml::from_str(input).unwrap_or_else(|err| panic!("Answers should be valid TOML: {err}"));
+    answers
+        .into_iter()
+        .map(|(key, value)| {
+            let (day, phase) = key
+                .split_once('.')
+                .and_then(|(day, phase)| Some((day.parse().ok()?, phase.parse().ok()?)))
+                .unwrap_or_else(|| panic!("Answer key '{key}' should be in the day.phase form"));
+            (day, phase, value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::{parse_answers, verify_day};
+
+    #[test]
+    fn checked_in_answers() {
+        let answers = fs::read_to_string("tasks/answers.toml").expect("Answers file should exist");
+        for (day, phase, expected) in parse_answers(&answers) {
+            verify_day(day, phase, &expected);
+        }
+    }
+
+    #[test]
+    fn answer_keys() {
+        assert_eq!(
+            parse_answers("# comment\n\"12.2\" = \"525152\"\n"),
+            vec![(12, 2, "525152".to_string())]
+        );
+    }
+}